@@ -1,30 +1,64 @@
 use std::fmt;
-use crate::operator;
+use crate::{operator, span::Span, bigint::BigInt};
 
-/// 文 (statement) を表す enum.
+/// 文 (statement) を表すノード. ソースコード上の範囲を伴う.
 #[derive(Debug, Clone)]
-pub enum Statement {
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+impl Statement {
+    pub fn new(kind: StatementKind, span: Span) -> Self {
+        Statement { kind, span }
+    }
+}
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+/// 文の種類を表す enum.
+#[derive(Debug, Clone)]
+pub enum StatementKind {
     Let{ident: Expression, value: Expression},
+    /// 書き換えられない束縛. 値は常に null.
+    Const{ident: Expression, value: Expression},
+    /// 既存の束縛への代入. op があれば複合代入 (`x += 1` は `x = x + 1`). 値は常に null.
+    Assign{ident: Expression, op: Option<operator::Infix>, value: Expression},
     Return(Expression),
     Expression(Expression),
     Block(Vec<Statement>),
+    /// 値は常に null.
+    While{condition: Expression, body: Box<Statement>},
+    /// 配列の要素, 文字列の文字, ハッシュのキー (挿入順) を順に ident に束縛して body を実行する. 値は常に null.
+    For{ident: Expression, iterable: Expression, body: Box<Statement>},
+    Break,
+    Continue,
 }
-impl fmt::Display for Statement {
+impl fmt::Display for StatementKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Statement::Let{ident, value} => write!(f, "let {} = {};", ident, value),
-            Statement::Return(value)     => write!(f, "return {};", value),
-            Statement::Expression(value) => write!(f, "{};", value),
-            Statement::Block(blocks)     => {
+            StatementKind::Let{ident, value} => write!(f, "let {} = {};", ident, value),
+            StatementKind::Const{ident, value} => write!(f, "const {} = {};", ident, value),
+            StatementKind::Assign{ident, op: None, value}     => write!(f, "{} = {};", ident, value),
+            StatementKind::Assign{ident, op: Some(op), value} => write!(f, "{} {}= {};", ident, op, value),
+            StatementKind::Return(value)     => write!(f, "return {};", value),
+            StatementKind::Expression(value) => write!(f, "{};", value),
+            StatementKind::Block(blocks)     => {
                 for stmt in blocks.iter() { write!(f, "{}", stmt)?; }
                 Ok(())
             },
+            StatementKind::While{condition, body} => write!(f, "while({}){{{}}}", condition, body),
+            StatementKind::For{ident, iterable, body} => write!(f, "for({} in {}){{{}}}", ident, iterable, body),
+            StatementKind::Break              => write!(f, "break;"),
+            StatementKind::Continue           => write!(f, "continue;"),
         }
     }
 }
 
 /// AST のルートノード
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Program {
     pub(crate) statements: Vec<Statement>,
 }
@@ -34,53 +68,87 @@ impl Program {
 impl fmt::Display for Program {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for stmt in self.statements.iter() {
-            write!(f, "{}\n", stmt)?;
+            writeln!(f, "{}", stmt)?;
         }
         Ok(())
     }
 }
 
-/// 式 (expression) を表す enum.
+/// 式 (expression) を表すノード. ソースコード上の範囲を伴う.
 #[derive(Debug, Clone)]
-pub enum Expression {
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+impl Expression {
+    pub fn new(kind: ExpressionKind, span: Span) -> Self {
+        Expression { kind, span }
+    }
+}
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+/// 式の種類を表す enum.
+#[derive(Debug, Clone)]
+pub enum ExpressionKind {
     Ident(String),
     String(String),
-    Integer(i32),
+    Integer(i64),
+    BigInteger(BigInt),
+    Float(f64),
     Bool(bool),
     Prefix {op: operator::Prefix, right: Box<Expression>},
     Infix  {op: operator::Infix,  left: Box<Expression>, right: Box<Expression>},
+    /// `&&` と `||`. 右辺は必要なときだけ評価される.
+    Logical{op: operator::Logical, left: Box<Expression>, right: Box<Expression>},
     If       {condition: Box<Expression>, consequence: Box<Statement>, alternative: Option<Box<Statement>>},
     Function {parameters: Vec<Expression>, body: Box<Statement>},
     Call     {function: Box<Expression>, arguments: Vec<Expression>},
+    Array(Vec<Expression>),
+    Hash(Vec<(Expression, Expression)>),
+    Index    {left: Box<Expression>, index: Box<Expression>},
 }
-impl fmt::Display for Expression {
+impl fmt::Display for ExpressionKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Expression::Ident(value) => write!(f, "{}", &value),
-            Expression::String(value) => write!(f, "{}", &value),
-            Expression::Integer(value) => write!(f, "{}", value),
-            Expression::Bool(value) => write!(f, "{}", value),
-            Expression::Prefix{op, right} => write!(f, "({}{})", op, right),
-            Expression::Infix{op, left, right} => write!(f, "({}{}{})", left, op, right),
-            Expression::If{condition, consequence, alternative} => {
+            ExpressionKind::Ident(value) => write!(f, "{}", &value),
+            ExpressionKind::String(value) => write!(f, "{}", &value),
+            ExpressionKind::Integer(value) => write!(f, "{}", value),
+            ExpressionKind::BigInteger(value) => write!(f, "{}", value),
+            ExpressionKind::Float(value) => write!(f, "{:?}", value),
+            ExpressionKind::Bool(value) => write!(f, "{}", value),
+            ExpressionKind::Prefix{op, right} => write!(f, "({}{})", op, right),
+            ExpressionKind::Infix{op, left, right} => write!(f, "({}{}{})", left, op, right),
+            ExpressionKind::Logical{op, left, right} => write!(f, "({}{}{})", left, op, right),
+            ExpressionKind::If{condition, consequence, alternative} => {
                 match alternative {
                     Some(alt) => write!(f, "if({}){{{}}}else{{{}}}", condition, consequence, alt),
                     None => write!(f, "if({}){{{}}}", condition, consequence),
                 }
             },
-            Expression::Function{parameters, body} => {
+            ExpressionKind::Function{parameters, body} => {
                 write!(f, "fn({}){{{}}}", 
-                    parameters.iter().map(|expr| -> &str { match expr {
-                            Expression::Ident(ident) => ident,
+                    parameters.iter().map(|expr| -> &str { match &expr.kind {
+                            ExpressionKind::Ident(ident) => ident,
                             _ => unreachable!(),
                     }}).collect::<Vec<_>>().join(","),
                     body
                 )
             },
-            Expression::Call{function, arguments} => write!(f, "{}({})",
+            ExpressionKind::Call{function, arguments} => write!(f, "{}({})",
                 function,
                 arguments.iter().map(|expr| format!("{}", &expr)).collect::<Vec<_>>().join(","),
             ),
+            ExpressionKind::Array(elements) => write!(f, "[{}]",
+                elements.iter().map(|expr| format!("{}", &expr)).collect::<Vec<_>>().join(","),
+            ),
+            ExpressionKind::Hash(pairs) => write!(f, "{{{}}}",
+                pairs.iter().map(|(key, value)| format!("{}:{}", key, value)).collect::<Vec<_>>().join(","),
+            ),
+            ExpressionKind::Index{left, index} => write!(f, "({}[{}])", left, index),
         }
     }
 }
@@ -0,0 +1,498 @@
+use std::{cmp::Ordering, convert::TryFrom, fmt, ops};
+
+/// 任意精度の符号付き整数. i64 に収まらない整数の演算に使う.
+/// 絶対値を 2^32 進数で下位の桁から保持する. 最上位の桁は 0 でなく, 0 は空の桁列で符号は正とする.
+/// この正規形を保つので, 導出した Eq と Hash がそのまま値の等価性になる.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+impl BigInt {
+    fn from_parts(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        trim(&mut magnitude);
+        let negative = negative && !magnitude.is_empty();
+        BigInt { negative, magnitude }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_odd(&self) -> bool {
+        self.magnitude.first().is_some_and(|digit| digit & 1 == 1)
+    }
+
+    /// 絶対値を表すのに必要なビット数. 0 なら 0.
+    pub fn bits(&self) -> u64 {
+        match self.magnitude.last() {
+            Some(top) => self.magnitude.len() as u64 * 32 - top.leading_zeros() as u64,
+            None      => 0,
+        }
+    }
+
+    /// `radix` 進数の符号のない数字列から生成する. 空であるか数字でない文字を含めば `None` を返す.
+    pub fn from_str_radix(digits: &str, radix: u32) -> Option<Self> {
+        if digits.is_empty() {
+            return None;
+        }
+        let mut magnitude = Vec::new();
+        for c in digits.chars() {
+            mul_small_add(&mut magnitude, radix, c.to_digit(radix)?);
+        }
+        Some(BigInt::from_parts(false, magnitude))
+    }
+
+    /// i64 に収まれば変換する.
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+        let abs = self.magnitude.iter().rev().fold(0u64, |acc, digit| (acc << 32) | *digit as u64);
+        if !self.negative {
+            i64::try_from(abs).ok()
+        } else if abs <= 1 << 63 {
+            Some((abs as i64).wrapping_neg())
+        } else {
+            None
+        }
+    }
+
+    /// f64 に変換する. 精度は落ち, 大きすぎれば無限大になる.
+    pub fn to_f64(&self) -> f64 {
+        let abs = self.magnitude.iter().rev().fold(0.0, |acc, digit| acc * 4294967296.0 + *digit as f64);
+        if self.negative { -abs } else { abs }
+    }
+
+    /// 0 方向に切り捨てる除算の商と余りを返す. 余りの符号は被除数と同じになる.
+    /// 除数が 0 なら `None` を返す.
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_magnitude(&self.magnitude, &other.magnitude);
+        Some((
+            BigInt::from_parts(self.negative != other.negative, quotient),
+            BigInt::from_parts(self.negative, remainder),
+        ))
+    }
+
+    /// exp 乗を返す.
+    pub fn pow(&self, mut exp: u32) -> BigInt {
+        let mut base = self.clone();
+        let mut result = BigInt::from(1);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = &result * &base;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+
+    /// 2 の補数表現を len 桁に符号拡張した桁列を返す. len は絶対値の桁数より大きくなければならない.
+    fn to_twos_complement(&self, len: usize) -> Vec<u32> {
+        let mut digits = self.magnitude.clone();
+        digits.resize(len, 0);
+        if self.negative {
+            // -x は !(x - 1)
+            sub_magnitude(&digits, &[1]).iter().map(|digit| !digit).collect()
+        } else {
+            digits
+        }
+    }
+
+    fn from_twos_complement(digits: Vec<u32>) -> BigInt {
+        if digits.last().is_some_and(|top| top >> 31 == 1) {
+            let inverted: Vec<u32> = digits.iter().map(|digit| !digit).collect();
+            BigInt::from_parts(true, add_magnitude(&inverted, &[1]))
+        } else {
+            BigInt::from_parts(false, digits)
+        }
+    }
+
+    /// 2 の補数表現の桁ごとに f を適用する.
+    fn bitwise(&self, other: &BigInt, f: fn(u32, u32) -> u32) -> BigInt {
+        let len = self.magnitude.len().max(other.magnitude.len()) + 1;
+        let (a, b) = (self.to_twos_complement(len), other.to_twos_complement(len));
+        BigInt::from_twos_complement(a.iter().zip(b.iter()).map(|(x, y)| f(*x, *y)).collect())
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let abs = value.unsigned_abs();
+        BigInt::from_parts(value < 0, vec![abs as u32, (abs >> 32) as u32])
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true)  => Ordering::Greater,
+            (true, false)  => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true)   => cmp_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ops::Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.magnitude)
+    }
+}
+
+impl ops::Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(self.negative, add_magnitude(&self.magnitude, &other.magnitude));
+        }
+        // 符号が異なれば絶対値の大きい方から小さい方を引く
+        match cmp_magnitude(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::from_parts(other.negative, sub_magnitude(&other.magnitude, &self.magnitude)),
+            _              => BigInt::from_parts(self.negative, sub_magnitude(&self.magnitude, &other.magnitude)),
+        }
+    }
+}
+
+impl ops::Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other.clone()
+    }
+}
+
+impl ops::Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::from_parts(self.negative != other.negative, mul_magnitude(&self.magnitude, &other.magnitude))
+    }
+}
+
+/// ビット演算は無限に符号拡張した 2 の補数表現に対して行う.
+impl ops::BitAnd for &BigInt {
+    type Output = BigInt;
+
+    fn bitand(self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |x, y| x & y)
+    }
+}
+
+impl ops::BitOr for &BigInt {
+    type Output = BigInt;
+
+    fn bitor(self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |x, y| x | y)
+    }
+}
+
+impl ops::BitXor for &BigInt {
+    type Output = BigInt;
+
+    fn bitxor(self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |x, y| x ^ y)
+    }
+}
+
+impl ops::Shl<usize> for &BigInt {
+    type Output = BigInt;
+
+    fn shl(self, n: usize) -> BigInt {
+        let mut magnitude = vec![0u32; n / 32];
+        let mut carry = 0u32;
+        for digit in self.magnitude.iter() {
+            let shifted = (*digit as u64) << (n % 32);
+            magnitude.push(shifted as u32 | carry);
+            carry = (shifted >> 32) as u32;
+        }
+        magnitude.push(carry);
+        BigInt::from_parts(self.negative, magnitude)
+    }
+}
+
+/// 算術右シフト. 負の数は負の無限大方向に丸める.
+impl ops::Shr<usize> for &BigInt {
+    type Output = BigInt;
+
+    fn shr(self, n: usize) -> BigInt {
+        if self.negative {
+            // x >> n は -(((-x - 1) >> n) + 1)
+            let shifted = BigInt::from_parts(false, shr_magnitude(&sub_magnitude(&self.magnitude, &[1]), n));
+            -(&shifted + &BigInt::from(1))
+        } else {
+            BigInt::from_parts(false, shr_magnitude(&self.magnitude, n))
+        }
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // 10^9 で割り続けて下位から 9 桁ずつ取り出す
+        let mut chunks = Vec::new();
+        let mut magnitude = self.magnitude.clone();
+        while !magnitude.is_empty() {
+            chunks.push(div_small(&mut magnitude, 1_000_000_000));
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        if let Some(first) = chunks.next() {
+            write!(f, "{}", first)?;
+        }
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+/// 上位の 0 の桁を取り除く.
+fn trim(magnitude: &mut Vec<u32>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+/// 正規形の絶対値同士を比較する.
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+/// a - b を計算する. a >= b でなければならない.
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, digit) in a.iter().enumerate() {
+        let mut diff = *digit as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += 1 << 32;
+            borrow = 1;
+        }
+        result.push(diff as u32);
+    }
+    result
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, y) in b.iter().enumerate() {
+            let t = *x as u64 * *y as u64 + result[i + j] as u64 + carry;
+            result[i + j] = t as u32;
+            carry = t >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+    result
+}
+
+fn shr_magnitude(a: &[u32], n: usize) -> Vec<u32> {
+    let a = match a.get(n / 32..) {
+        Some(a) => a,
+        None    => return Vec::new(),
+    };
+    (0..a.len()).map(|i| {
+        let pair = (*a.get(i + 1).unwrap_or(&0) as u64) << 32 | a[i] as u64;
+        (pair >> (n % 32)) as u32
+    }).collect()
+}
+
+/// magnitude を magnitude * m + a で置き換える.
+fn mul_small_add(magnitude: &mut Vec<u32>, m: u32, a: u32) {
+    let mut carry = a as u64;
+    for digit in magnitude.iter_mut() {
+        let t = *digit as u64 * m as u64 + carry;
+        *digit = t as u32;
+        carry = t >> 32;
+    }
+    if carry > 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+/// magnitude を d で割った商で置き換え, 余りを返す.
+fn div_small(magnitude: &mut Vec<u32>, d: u32) -> u32 {
+    let mut remainder = 0u64;
+    for digit in magnitude.iter_mut().rev() {
+        let cur = (remainder << 32) | *digit as u64;
+        *digit = (cur / d as u64) as u32;
+        remainder = cur % d as u64;
+    }
+    trim(magnitude);
+    remainder as u32
+}
+
+/// 絶対値の商と余りを 2 進の筆算で求める. b は 0 でない正規形でなければならない.
+fn div_rem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if b.len() == 1 {
+        let mut quotient = a.to_vec();
+        let remainder = div_small(&mut quotient, b[0]);
+        return (quotient, vec![remainder]);
+    }
+    if cmp_magnitude(a, b) == Ordering::Less {
+        return (Vec::new(), a.to_vec());
+    }
+
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+    for i in (0..a.len() * 32).rev() {
+        // remainder = remainder * 2 + (a の i ビット目)
+        let mut carry = (a[i / 32] >> (i % 32)) & 1;
+        for digit in remainder.iter_mut() {
+            let next = *digit >> 31;
+            *digit = (*digit << 1) | carry;
+            carry = next;
+        }
+        if carry > 0 {
+            remainder.push(carry);
+        }
+
+        if cmp_magnitude(&remainder, b) != Ordering::Less {
+            remainder = sub_magnitude(&remainder, b);
+            trim(&mut remainder);
+            quotient[i / 32] |= 1 << (i % 32);
+        }
+    }
+    (quotient, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bigint::BigInt;
+
+    fn big(s: &str) -> BigInt {
+        match s.strip_prefix('-') {
+            Some(digits) => -BigInt::from_str_radix(digits, 10).unwrap(),
+            None         => BigInt::from_str_radix(s, 10).unwrap(),
+        }
+    }
+
+    #[test]
+    fn parse_and_display() {
+        let inputs = ["0", "1", "4294967296", "9223372036854775808", "123456789012345678901234567890", "-1000000000000000000000"];
+        for input in inputs.iter() {
+            assert_eq!( big(input).to_string(), *input );
+        }
+        assert_eq!( BigInt::from_str_radix("ff", 16), Some(BigInt::from(255)) );
+        assert_eq!( BigInt::from_str_radix("000", 10), Some(BigInt::default()) );
+        assert_eq!( BigInt::from_str_radix("12a", 10), None );
+        assert_eq!( BigInt::from_str_radix("", 10), None );
+        assert_eq!( -BigInt::default(), BigInt::default() );
+    }
+
+    #[test]
+    fn conversions() {
+        for value in [0, 1, -1, i64::MAX, i64::MIN, 1 << 32, -(1 << 32) - 1].iter() {
+            assert_eq!( BigInt::from(*value).to_i64(), Some(*value) );
+            assert_eq!( BigInt::from(*value).to_string(), value.to_string() );
+        }
+        assert_eq!( big("9223372036854775808").to_i64(), None );
+        assert_eq!( big("-9223372036854775809").to_i64(), None );
+        assert_eq!( big("18446744073709551616").to_f64(), 18446744073709551616.0 );
+        assert_eq!( big("-4294967296").to_f64(), -4294967296.0 );
+    }
+
+    #[test]
+    fn arithmetic() {
+        // i128 で計算した結果と比較する
+        let values: [i128; 8] = [0, 1, -7, 4294967295, -4294967296, i64::MAX as i128 + 3, i64::MIN as i128 * 5, 170141183460469231731687303715884105];
+        for x in values.iter() {
+            for y in values.iter() {
+                let (a, b) = (big(&x.to_string()), big(&y.to_string()));
+                assert_eq!( (&a + &b).to_string(), (x + y).to_string(), "{} + {}", x, y );
+                assert_eq!( (&a - &b).to_string(), (x - y).to_string(), "{} - {}", x, y );
+                assert_eq!( a.cmp(&b), x.cmp(y), "{} <=> {}", x, y );
+                if let Some(product) = x.checked_mul(*y) {
+                    assert_eq!( (&a * &b).to_string(), product.to_string(), "{} * {}", x, y );
+                }
+                match a.div_rem(&b) {
+                    Some((q, r)) => {
+                        assert_eq!( q.to_string(), (x / y).to_string(), "{} / {}", x, y );
+                        assert_eq!( r.to_string(), (x % y).to_string(), "{} % {}", x, y );
+                    },
+                    None => assert_eq!( *y, 0 ),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bit_operations() {
+        let values: [i128; 8] = [0, 1, -1, -7, 4294967295, -4294967296, i64::MIN as i128 * 5, 170141183460469231731687303715884105];
+        for x in values.iter() {
+            for y in values.iter() {
+                let (a, b) = (big(&x.to_string()), big(&y.to_string()));
+                assert_eq!( (&a & &b).to_string(), (x & y).to_string(), "{} & {}", x, y );
+                assert_eq!( (&a | &b).to_string(), (x | y).to_string(), "{} | {}", x, y );
+                assert_eq!( (&a ^ &b).to_string(), (x ^ y).to_string(), "{} ^ {}", x, y );
+            }
+            for n in [0usize, 1, 5, 31, 32, 33, 64, 200].iter() {
+                assert_eq!( (&from_i128(x) >> *n).to_string(), (x >> (*n).min(127)).to_string(), "{} >> {}", x, n );
+                if x.checked_shl(*n as u32).is_some_and(|shifted| shifted >> *n == *x) {
+                    assert_eq!( (&from_i128(x) << *n).to_string(), (x << *n).to_string(), "{} << {}", x, n );
+                }
+            }
+            assert_eq!( from_i128(x).is_odd(), x % 2 != 0 );
+            assert_eq!( from_i128(x).bits(), 128 - x.unsigned_abs().leading_zeros() as u64 );
+        }
+        assert_eq!( (&BigInt::from(1) << 100).to_string(), "1267650600228229401496703205376" );
+        assert_eq!( BigInt::from(3).pow(50).to_string(), "717897987691852588770249" );
+        assert_eq!( BigInt::from(-2).pow(63), BigInt::from(i64::MIN) );
+        assert_eq!( BigInt::from(5).pow(0), BigInt::from(1) );
+    }
+
+    fn from_i128(x: &i128) -> BigInt {
+        big(&x.to_string())
+    }
+
+    #[test]
+    fn large_arithmetic() {
+        let a = big("123456789012345678901234567890123456789");
+        let b = big("987654321098765432109876543210");
+        let product = &a * &b;
+        assert_eq!( product.to_string(), "121932631137021795226185032733744855963362292333223746380111126352690" );
+        let (q, r) = product.div_rem(&b).unwrap();
+        assert_eq!( (q, r), (a.clone(), BigInt::default()) );
+        let (q, r) = (&product + &BigInt::from(5)).div_rem(&a).unwrap();
+        assert_eq!( (q, r), (b, BigInt::from(5)) );
+    }
+}
@@ -0,0 +1,172 @@
+use std::{fmt, rc::Rc};
+use crate::{
+    object::Object,
+    env::Environment,
+    error::MonkeyError,
+    span::Span,
+};
+
+/// 組み込み関数の本体. 引数を受け取り結果を返す.
+/// 返すエラーの発生箇所はダミーとし, 呼び出し側で関数呼び出し式の位置を設定する.
+/// ホスト (Rust) 側のクロージャも登録できるよう Rc<dyn Fn> で保持する.
+pub type BuiltinFunction = Rc<dyn Fn(&[Object]) -> Result<Object, MonkeyError>>;
+
+/// 組み込み関数が受け取る引数の数.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exact(usize),
+    /// 最小と最大の間 (両端を含む) の数
+    Between(usize, usize),
+    Variadic,
+}
+
+/// 組み込み関数. Object::Builtin として環境に束縛される.
+#[derive(Clone)]
+pub struct Builtin {
+    pub name: String,
+    pub arity: Arity,
+    pub func: BuiltinFunction,
+}
+impl Builtin {
+    pub fn new<F>(name: &str, arity: Arity, func: F) -> Self
+        where F: Fn(&[Object]) -> Result<Object, MonkeyError> + 'static
+    {
+        Builtin { name: name.to_owned(), arity, func: Rc::new(func) }
+    }
+
+    /// 引数の数を確かめてから関数を呼び出す.
+    pub fn call(&self, args: &[Object]) -> Result<Object, MonkeyError> {
+        match self.arity {
+            Arity::Exact(expected) if args.len() != expected => {
+                return Err(MonkeyError::IncorrectNumberOfArgs{ expected, got: args.len(), span: Span::default() });
+            },
+            Arity::Between(min, max) if args.len() < min || args.len() > max => {
+                return Err(MonkeyError::IncorrectNumberOfArgsRange{ min, max, got: args.len(), span: Span::default() });
+            },
+            _ => {},
+        }
+        (self.func)(args)
+    }
+}
+impl fmt::Debug for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Builtin").field("name", &self.name).field("arity", &self.arity).finish()
+    }
+}
+impl PartialEq for Builtin {
+    /// 同じ関数本体を共有しているときに限り等しい.
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && Rc::ptr_eq(&self.func, &other.func)
+    }
+}
+
+type NativeFunction = fn(&[Object]) -> Result<Object, MonkeyError>;
+
+/// 言語に組み込まれている関数の一覧.
+const BUILTINS: &[(&str, Arity, NativeFunction)] = &[
+    ("puts",  Arity::Variadic, puts ),
+    ("len",   Arity::Exact(1), len  ),
+    ("first", Arity::Exact(1), first),
+    ("last",  Arity::Exact(1), last ),
+    ("rest",  Arity::Exact(1), rest ),
+    ("push",  Arity::Exact(2), push ),
+    ("range", Arity::Between(1, 3), range),
+];
+
+/// すべての組み込み関数を一定の順序で返す.
+pub fn all() -> impl Iterator<Item = Builtin> {
+    BUILTINS.iter().map(|(name, arity, func)| Builtin::new(name, *arity, *func))
+}
+
+/// すべての組み込み関数を環境に束縛する.
+pub fn register(env: &mut Environment) {
+    for builtin in all() {
+        env.set(builtin.name.clone(), Object::Builtin(builtin));
+    }
+}
+
+fn invalid_argument(function: &str, arg: &Object) -> MonkeyError {
+    MonkeyError::InvalidArgument{ function: function.to_owned(), got: arg.type_of(), span: Span::default() }
+}
+
+fn puts(args: &[Object]) -> Result<Object, MonkeyError> {
+    println!("{}", args.iter().map(|arg| format!("{} ", arg)).collect::<String>());
+    Ok(Object::Null)
+}
+
+fn len(args: &[Object]) -> Result<Object, MonkeyError> {
+    match &args[0] {
+        Object::String(s)        => Ok(Object::Integer(s.chars().count() as i64)),
+        Object::Array(elements)  => Ok(Object::Integer(elements.len() as i64)),
+        arg                      => Err(invalid_argument("len", arg)),
+    }
+}
+
+fn first(args: &[Object]) -> Result<Object, MonkeyError> {
+    match &args[0] {
+        Object::Array(elements) => Ok(elements.first().cloned().unwrap_or(Object::Null)),
+        arg                     => Err(invalid_argument("first", arg)),
+    }
+}
+
+fn last(args: &[Object]) -> Result<Object, MonkeyError> {
+    match &args[0] {
+        Object::Array(elements) => Ok(elements.last().cloned().unwrap_or(Object::Null)),
+        arg                     => Err(invalid_argument("last", arg)),
+    }
+}
+
+/// 先頭の要素を除いた新しい配列を返す. 空の配列に対しては null を返す.
+fn rest(args: &[Object]) -> Result<Object, MonkeyError> {
+    match &args[0] {
+        Object::Array(elements) if elements.is_empty() => Ok(Object::Null),
+        Object::Array(elements) => Ok(Object::Array(elements[1..].to_vec())),
+        arg                     => Err(invalid_argument("rest", arg)),
+    }
+}
+
+/// 末尾に要素を追加した新しい配列を返す. 元の配列は変更しない.
+fn push(args: &[Object]) -> Result<Object, MonkeyError> {
+    match &args[0] {
+        Object::Array(elements) => {
+            let mut elements = elements.clone();
+            elements.push(args[1].clone());
+            Ok(Object::Array(elements))
+        },
+        arg => Err(invalid_argument("push", arg)),
+    }
+}
+
+/// `range` が返す配列の要素数の上限.
+const MAX_RANGE_LEN: u64 = 1 << 22;
+
+/// start 以上 end 未満 (step が負なら start 以下 end 超) の整数を step 刻みで並べた配列を返す.
+/// `range(end)` は start = 0, `range(start, end)` は step = 1 とみなす.
+fn range(args: &[Object]) -> Result<Object, MonkeyError> {
+    let mut bounds = Vec::with_capacity(args.len());
+    for arg in args.iter() {
+        match arg {
+            Object::Integer(value) => bounds.push(*value),
+            arg                    => return Err(invalid_argument("range", arg)),
+        }
+    }
+    let (start, end, step) = match bounds[..] {
+        [end]              => (0, end, 1),
+        [start, end]       => (start, end, 1),
+        [start, end, step] => (start, end, step),
+        // 引数の数は Arity::Between(1, 3) で確かめている
+        _ => unreachable!(),
+    };
+    if step == 0 {
+        return Err(MonkeyError::ZeroStep(Span::default()));
+    }
+
+    // i128 で計算すれば桁あふれしない
+    let (start, end, step) = (start as i128, end as i128, step as i128);
+    let len = if step > 0 { (end - start + step - 1) / step } else { (start - end - step - 1) / -step };
+    let len = len.max(0) as u64;
+    if len > MAX_RANGE_LEN {
+        return Err(MonkeyError::TooLarge{ what: "array", span: Span::default() });
+    }
+    Ok(Object::Array((0..len as i128).map(|i| Object::Integer((start + i * step) as i64)).collect()))
+}
@@ -0,0 +1,212 @@
+use std::fmt;
+use crate::operator;
+
+/// バイトコードの命令列.
+pub type Instructions = Vec<u8>;
+
+/// 命令の種類. 1 バイトの命令コードの後にオペランドが続く. オペランドはビッグエンディアン.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Opcode {
+    /// 定数プールの値を積む. オペランド: 定数のインデックス (2 バイト)
+    Constant,
+    /// スタックの先頭を捨てる.
+    Pop,
+    True,
+    False,
+    Null,
+    /// 前置演算子を適用する. オペランド: 演算子の番号 (1 バイト)
+    Prefix,
+    /// 中置演算子を適用する. オペランド: 演算子の番号 (1 バイト)
+    Infix,
+    /// 無条件にジャンプする. オペランド: ジャンプ先 (2 バイト)
+    Jump,
+    /// スタックの先頭を取り出し, 偽ならジャンプする. オペランド: ジャンプ先 (2 バイト)
+    JumpNotTruthy,
+    /// スタックの先頭が偽ならそれを残してジャンプし, 真なら取り除く (`&&`). オペランド: ジャンプ先 (2 バイト)
+    JumpIfFalsy,
+    /// スタックの先頭が真ならそれを残してジャンプし, 偽なら取り除く (`||`). オペランド: ジャンプ先 (2 バイト)
+    JumpIfTruthy,
+    /// オペランド: グローバル変数の番号 (2 バイト)
+    GetGlobal,
+    SetGlobal,
+    /// オペランド: 局所変数の番号 (1 バイト)
+    GetLocal,
+    SetLocal,
+    /// `const` で束縛する. 以後この変数に Assign 系の命令で代入すると AssignToConstant になる.
+    /// オペランド: SetConstGlobal は 2 バイト, SetConstLocal は 1 バイトの番号
+    SetConstGlobal,
+    SetConstLocal,
+    /// クロージャが捕捉した変数を積む. オペランド: 自由変数の番号 (1 バイト)
+    GetFree,
+    /// スタックの先頭を取り出し, 既に束縛されている変数に代入する. 束縛されていなければエラーになる.
+    /// オペランド: AssignGlobal は 2 バイト, AssignLocal と AssignFree は 1 バイトの番号
+    AssignGlobal,
+    AssignLocal,
+    AssignFree,
+    /// 複合代入の代入先の値を積む. 束縛されていなければ (GetGlobal などと異なり) AssignToUndeclared になる.
+    /// オペランド: LoadGlobal は 2 バイト, LoadLocal と LoadFree は 1 バイトの番号
+    LoadGlobal,
+    LoadLocal,
+    LoadFree,
+    /// 局所変数を束縛されていない新しいセルに置き換える. ブロックに入るたびに実行する.
+    /// オペランド: 最初の局所変数の番号 (1 バイト), 個数 (1 バイト)
+    ResetLocals,
+    /// スタック上の要素から配列を作る. オペランド: 要素数 (2 バイト)
+    Array,
+    /// スタック上のキーと値の組からハッシュを作る. オペランド: 組の数 (2 バイト)
+    Hash,
+    Index,
+    /// 関数を呼び出す. オペランド: 引数の数 (1 バイト)
+    Call,
+    /// スタックの先頭を戻り値として関数から戻る.
+    ReturnValue,
+    /// 定数プールの関数からクロージャを作る. オペランド: 定数のインデックス (2 バイト)
+    Closure,
+    /// スタックの先頭を取り出し, for 文が順に取り出す値の配列と次の添字 0 を積む.
+    Iterate,
+    /// Iterate が積んだ配列から次の値を取り出して積む. 残っていなければ配列と添字を取り除いてジャンプする.
+    /// オペランド: ジャンプ先 (2 バイト)
+    Next,
+}
+
+const OPCODES: &[Opcode] = &[
+    Opcode::Constant, Opcode::Pop, Opcode::True, Opcode::False, Opcode::Null,
+    Opcode::Prefix, Opcode::Infix, Opcode::Jump, Opcode::JumpNotTruthy, Opcode::JumpIfFalsy, Opcode::JumpIfTruthy,
+    Opcode::GetGlobal, Opcode::SetGlobal, Opcode::GetLocal, Opcode::SetLocal,
+    Opcode::SetConstGlobal, Opcode::SetConstLocal, Opcode::GetFree,
+    Opcode::AssignGlobal, Opcode::AssignLocal, Opcode::AssignFree,
+    Opcode::LoadGlobal, Opcode::LoadLocal, Opcode::LoadFree, Opcode::ResetLocals,
+    Opcode::Array, Opcode::Hash, Opcode::Index, Opcode::Call, Opcode::ReturnValue, Opcode::Closure,
+    Opcode::Iterate, Opcode::Next,
+];
+
+impl Opcode {
+    /// 命令コードのバイトから Opcode を得る.
+    pub fn from_byte(byte: u8) -> Option<Opcode> {
+        OPCODES.get(byte as usize).copied()
+    }
+
+    /// 各オペランドのバイト数.
+    pub fn operand_widths(self) -> &'static [usize] {
+        match self {
+            Opcode::Constant | Opcode::Jump | Opcode::JumpNotTruthy | Opcode::JumpIfFalsy | Opcode::JumpIfTruthy | Opcode::Next
+                | Opcode::GetGlobal | Opcode::SetGlobal | Opcode::SetConstGlobal | Opcode::AssignGlobal | Opcode::LoadGlobal
+                | Opcode::Array | Opcode::Hash | Opcode::Closure => &[2],
+            Opcode::Prefix | Opcode::Infix
+                | Opcode::GetLocal | Opcode::SetLocal | Opcode::SetConstLocal | Opcode::GetFree | Opcode::AssignLocal | Opcode::AssignFree
+                | Opcode::LoadLocal | Opcode::LoadFree
+                | Opcode::Call => &[1],
+            Opcode::ResetLocals => &[1, 1],
+            Opcode::Pop | Opcode::True | Opcode::False | Opcode::Null
+                | Opcode::Index | Opcode::ReturnValue | Opcode::Iterate => &[],
+        }
+    }
+}
+
+/// 命令を一つエンコードする.
+pub fn make(op: Opcode, operands: &[usize]) -> Instructions {
+    let widths = op.operand_widths();
+    let mut instruction = vec![op as u8];
+    for (operand, width) in operands.iter().zip(widths.iter()) {
+        match width {
+            2 => instruction.extend_from_slice(&(*operand as u16).to_be_bytes()),
+            1 => instruction.push(*operand as u8),
+            _ => unreachable!(),
+        }
+    }
+    instruction
+}
+
+pub fn read_u16(ins: &[u8], offset: usize) -> usize {
+    u16::from_be_bytes([ins[offset], ins[offset + 1]]) as usize
+}
+
+pub fn read_u8(ins: &[u8], offset: usize) -> usize {
+    ins[offset] as usize
+}
+
+/// Opcode::Prefix のオペランドで表す前置演算子.
+pub const PREFIX_OPERATORS: &[operator::Prefix] = &[
+    operator::Prefix::Bang,
+    operator::Prefix::Minus,
+];
+
+/// Opcode::Infix のオペランドで表す中置演算子.
+pub const INFIX_OPERATORS: &[operator::Infix] = &[
+    operator::Infix::Plus,
+    operator::Infix::Minus,
+    operator::Infix::Asterisk,
+    operator::Infix::Slash,
+    operator::Infix::Eq,
+    operator::Infix::NotEq,
+    operator::Infix::LT,
+    operator::Infix::GT,
+    operator::Infix::Percent,
+    operator::Infix::Power,
+    operator::Infix::LTEq,
+    operator::Infix::GTEq,
+    operator::Infix::BitAnd,
+    operator::Infix::BitOr,
+    operator::Infix::BitXor,
+    operator::Infix::Shl,
+    operator::Infix::Shr,
+];
+
+/// 命令列を人が読める形式で表示するためのラッパー.
+pub struct Disassembly<'a>(pub &'a [u8]);
+impl<'a> fmt::Display for Disassembly<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ins = self.0;
+        let mut ip = 0;
+        while ip < ins.len() {
+            let op = match Opcode::from_byte(ins[ip]) {
+                Some(op) => op,
+                None => return writeln!(f, "{:04} ?{}", ip, ins[ip]),
+            };
+            write!(f, "{:04} {:?}", ip, op)?;
+            let mut offset = ip + 1;
+            for width in op.operand_widths() {
+                match width {
+                    2 => write!(f, " {}", read_u16(ins, offset))?,
+                    _ => write!(f, " {}", read_u8(ins, offset))?,
+                }
+                offset += width;
+            }
+            writeln!(f)?;
+            ip = offset;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::code::{make, Opcode, Disassembly};
+
+    #[test]
+    fn test_make() {
+        assert_eq!( make(Opcode::Constant, &[65534]), vec![Opcode::Constant as u8, 255, 254] );
+        assert_eq!( make(Opcode::GetLocal, &[255]), vec![Opcode::GetLocal as u8, 255] );
+        assert_eq!( make(Opcode::Pop, &[]), vec![Opcode::Pop as u8] );
+    }
+
+    #[test]
+    fn test_opcode_from_byte() {
+        for byte in 0..=Opcode::Next as u8 {
+            assert_eq!( Opcode::from_byte(byte).map(|op| op as u8), Some(byte) );
+        }
+        assert_eq!( Opcode::from_byte(Opcode::Next as u8 + 1), None );
+    }
+
+    #[test]
+    fn test_disassembly() {
+        let ins: Vec<u8> = [
+            make(Opcode::Constant, &[1]),
+            make(Opcode::Infix, &[0]),
+            make(Opcode::Call, &[2]),
+            make(Opcode::Pop, &[]),
+        ].concat();
+        assert_eq!( format!("{}", Disassembly(&ins)), "0000 Constant 1\n0003 Infix 0\n0005 Call 2\n0007 Pop\n" );
+    }
+}
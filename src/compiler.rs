@@ -0,0 +1,937 @@
+//! AST をバイトコードに変換するコンパイラ.
+//!
+//! 名前の解決は evaluator と同じ結果になるよう次のように行う.
+//! - 関数本体の直下で `let` される名前と引数は, 関数本体をコンパイルする前にすべて局所変数として確保する.
+//!   そのため後で定義される関数を互いに呼び出すクロージャも作れる.
+//! - `if` や `while` などのブロックの直下で `let` される名前も, ブロックに入る前に新しい局所変数として確保する.
+//!   外側の同じ名前の変数は隠され, ブロックを出ると元に戻る. トップレベルのブロックでも局所変数になる.
+//!   ただし同じ関数の中では `let` より後でだけ見える (`let x = x + 1;` の右辺は外側の `x`).
+//!   ブロックの中の関数リテラルからは `let` より前でも見える.
+//! - ブロックに入るたびに (ループなら繰り返しごとに) そのブロックの局所変数を新しいセルに置き換える.
+//!   そのためブロックの中で作られたクロージャは, 作られたときのセルを捕捉する.
+//! - 局所変数はセルに格納され, クロージャはセルを参照で捕捉する.
+//! - 局所変数でも捕捉した変数でもない名前はグローバル変数とし, 参照された時点で番号を割り当てる.
+//!   値が束縛される前に実行時に参照されると IdentifierNotFound になる.
+//! - 代入は参照と同じように名前を解決し, 新しい変数は確保しない.
+//!   値が束縛される前に実行時に代入されると AssignToUndeclared になる. 複合代入で代入先を読むときも同じ.
+//! - `const` で束縛した変数への代入と, 同じ有効範囲での再宣言はコンパイル時に AssignToConstant になる.
+//!   `const` かどうかは文の順にコンパイルしながら記録するので, `const` より前にコンパイルされる代入
+//!   (`const` より前に書かれた関数の中の代入など) はコンパイル時には分からない.
+//!   そのような代入は実行時に仮想機械が束縛の `const` かどうかを確かめて AssignToConstant にする.
+
+use std::{rc::Rc, collections::{HashMap, HashSet}};
+use crate::{
+    ast::{self, Expression, ExpressionKind, Statement, StatementKind},
+    code::{self, Instructions, Opcode, PREFIX_OPERATORS, INFIX_OPERATORS},
+    object::Object,
+    error::MonkeyError,
+    span::Span,
+    operator,
+    builtins,
+};
+
+/// コンパイル済みの関数. プログラムのトップレベルもこの形にコンパイルする.
+#[derive(Debug)]
+pub struct CompiledFunction {
+    pub instructions: Instructions,
+    /// 失敗しうる命令の位置と, その命令に対応するソースコード上の範囲. 位置の昇順に並ぶ.
+    pub spans: Vec<(usize, Span)>,
+    pub num_parameters: usize,
+    /// 局所変数の名前. 添字が局所変数の番号.
+    pub local_names: Vec<String>,
+    /// クロージャ生成時に捕捉する変数. 添字が自由変数の番号.
+    pub captures: Vec<Capture>,
+    pub free_names: Vec<String>,
+    /// 関数リテラルのソースコード表現. 表示に用いる.
+    pub source: String,
+}
+impl CompiledFunction {
+    /// 位置 `ip` の命令に対応するソースコード上の範囲を返す.
+    pub fn span_at(&self, ip: usize) -> Span {
+        self.spans_at(ip).next().unwrap_or_default()
+    }
+
+    /// 位置 `ip` の命令に対応する範囲をすべて返す. Opcode::Hash は各キーの範囲を順に持つ.
+    pub fn spans_at(&self, ip: usize) -> impl Iterator<Item = Span> + '_ {
+        let start = self.spans.partition_point(|(offset, _)| *offset < ip);
+        self.spans[start..].iter().take_while(move |(offset, _)| *offset == ip).map(|(_, span)| *span)
+    }
+}
+
+/// クロージャが捕捉する変数の在処.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capture {
+    /// クロージャを生成する関数の局所変数
+    Local(usize),
+    /// クロージャを生成する関数がさらに捕捉している変数
+    Free(usize),
+}
+
+/// 定数プールの要素.
+#[derive(Debug, Clone)]
+pub enum Constant {
+    Object(Object),
+    Function(Rc<CompiledFunction>),
+}
+
+/// コンパイル結果.
+#[derive(Debug, Clone)]
+pub struct Bytecode {
+    pub main: Rc<CompiledFunction>,
+    pub constants: Vec<Constant>,
+    /// グローバル変数の名前. 添字がグローバル変数の番号で, 先頭には組み込み関数が並ぶ.
+    pub global_names: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Symbol {
+    Global(usize),
+    Local(usize),
+    Free(usize),
+}
+
+/// コンパイル中の関数一つ分の状態.
+#[derive(Debug, Default)]
+struct Scope {
+    instructions: Instructions,
+    spans: Vec<(usize, Span)>,
+    symbols: HashMap<String, Symbol>,
+    /// 囲んでいるブロックの局所変数の番号と, その `let` を既にコンパイルしたかどうか. 最も内側が末尾.
+    /// symbols より優先して解決する.
+    blocks: Vec<HashMap<String, (usize, bool)>>,
+    local_names: Vec<String>,
+    /// `const` で束縛した局所変数の番号.
+    constant_locals: HashSet<usize>,
+    captures: Vec<Capture>,
+    free_names: Vec<String>,
+    /// 後続の式の評価を待ってスタックに積まれている値の数. 演算子の左辺や関数呼び出しの引数など.
+    temporaries: usize,
+    /// 囲んでいるループ. 最も内側が末尾.
+    loops: Vec<Loop>,
+}
+
+/// コンパイル中のループ.
+#[derive(Debug)]
+struct Loop {
+    /// while 文の条件式または for 文の Opcode::Next の位置. continue のジャンプ先.
+    start: usize,
+    /// ループ本体に入ったときの Scope::temporaries. break と continue はこれを超える分を捨ててからジャンプする.
+    temporaries: usize,
+    /// ループ自身がスタックに積んでいる値の数 (for 文の配列と添字). break はこれも捨てる.
+    values: usize,
+    /// break のジャンプ命令の位置. ループの終わりが決まってから書き換える.
+    breaks: Vec<usize>,
+}
+
+/// コンパイラ. グローバル変数と定数プールは複数回の compile の間で引き継がれる (REPL 用).
+#[derive(Debug)]
+pub struct Compiler {
+    globals: HashMap<String, usize>,
+    global_names: Vec<String>,
+    /// `const` で束縛したグローバル変数の番号.
+    constant_globals: HashSet<usize>,
+    constants: Vec<Constant>,
+    scopes: Vec<Scope>,
+}
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Compiler {
+    pub fn new() -> Self {
+        let mut compiler = Compiler {
+            globals: HashMap::new(),
+            global_names: Vec::new(),
+            constant_globals: HashSet::new(),
+            constants: Vec::new(),
+            scopes: Vec::new(),
+        };
+        for builtin in builtins::all() {
+            compiler.global(&builtin.name);
+        }
+        compiler
+    }
+
+    /// プログラムをコンパイルする.
+    pub fn compile(&mut self, program: &ast::Program) -> Result<Bytecode, MonkeyError> {
+        self.scopes = vec![Scope::default()];
+
+        self.compile_statements(&program.statements)?;
+        let main = self.scopes.pop().unwrap();
+
+        Ok(Bytecode {
+            main: Rc::new(CompiledFunction {
+                instructions: main.instructions,
+                spans: main.spans,
+                num_parameters: 0,
+                local_names: main.local_names,
+                captures: Vec::new(),
+                free_names: Vec::new(),
+                source: String::new(),
+            }),
+            constants: self.constants.clone(),
+            global_names: self.global_names.clone(),
+        })
+    }
+
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().unwrap()
+    }
+
+    /// 命令を追加し, その位置を返す.
+    fn emit(&mut self, op: Opcode, operands: &[usize]) -> usize {
+        let scope = self.scope();
+        let pos = scope.instructions.len();
+        scope.instructions.extend(code::make(op, operands));
+        pos
+    }
+
+    /// 失敗しうる命令を, 対応するソースコード上の範囲とともに追加する.
+    fn emit_at(&mut self, op: Opcode, operands: &[usize], span: Span) -> usize {
+        let pos = self.emit(op, operands);
+        self.scope().spans.push((pos, span));
+        pos
+    }
+
+    /// 後続の式を評価する間スタックに残る値をコンパイルし, Scope::temporaries に数える.
+    /// 値を消費する命令を出力したら, その数だけ release する.
+    fn compile_operand(&mut self, expr: &Expression) -> Result<(), MonkeyError> {
+        self.compile_expression(expr)?;
+        self.scope().temporaries += 1;
+        Ok(())
+    }
+
+    fn release(&mut self, count: usize) {
+        self.scope().temporaries -= count;
+    }
+
+    /// 位置 `pos` のジャンプ命令のジャンプ先を現在の位置に書き換える.
+    /// 命令列が u16 に収まらなければ, compile_statement が文の終わりで CompileLimit を返す.
+    fn patch_jump(&mut self, pos: usize) {
+        let scope = self.scope();
+        let target = scope.instructions.len() as u16;
+        scope.instructions[pos + 1..pos + 3].copy_from_slice(&target.to_be_bytes());
+    }
+
+    fn add_constant(&mut self, constant: Constant, span: Span) -> Result<usize, MonkeyError> {
+        if self.constants.len() > u16::MAX as usize {
+            return Err(MonkeyError::CompileLimit{ what: "constants", span });
+        }
+        self.constants.push(constant);
+        Ok(self.constants.len() - 1)
+    }
+
+    /// グローバル変数の番号を返す. 未登録なら新たに割り当てる.
+    fn global(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.globals.get(name) {
+            return index;
+        }
+        let index = self.global_names.len();
+        self.globals.insert(name.to_owned(), index);
+        self.global_names.push(name.to_owned());
+        index
+    }
+
+    /// 現在の関数に局所変数を確保する. 既に確保されていればその番号を返す.
+    fn define_local(&mut self, name: &str, span: Span) -> Result<usize, MonkeyError> {
+        let scope = self.scope();
+        if let Some(Symbol::Local(index)) = scope.symbols.get(name) {
+            return Ok(*index);
+        }
+        let index = scope.local_names.len();
+        if index > u8::MAX as usize {
+            return Err(MonkeyError::CompileLimit{ what: "local variables", span });
+        }
+        scope.symbols.insert(name.to_owned(), Symbol::Local(index));
+        scope.local_names.push(name.to_owned());
+        Ok(index)
+    }
+
+    /// 最も内側のブロックに新しい局所変数を確保する. 同じブロックで既に確保されていればその番号を返す.
+    fn define_block_local(&mut self, name: &str, span: Span) -> Result<usize, MonkeyError> {
+        let scope = self.scope();
+        if let Some(&(index, _)) = scope.blocks.last().and_then(|block| block.get(name)) {
+            return Ok(index);
+        }
+        let index = scope.local_names.len();
+        if index > u8::MAX as usize {
+            return Err(MonkeyError::CompileLimit{ what: "local variables", span });
+        }
+        scope.blocks.last_mut().unwrap().insert(name.to_owned(), (index, false));
+        scope.local_names.push(name.to_owned());
+        Ok(index)
+    }
+
+    fn resolve(&mut self, name: &str, span: Span) -> Result<Symbol, MonkeyError> {
+        let depth = self.scopes.len() - 1;
+        match self.resolve_in(depth, name, span)? {
+            Some(symbol) => Ok(symbol),
+            None => {
+                let index = self.global(name);
+                if index > u16::MAX as usize {
+                    return Err(MonkeyError::CompileLimit{ what: "global variables", span });
+                }
+                Ok(Symbol::Global(index))
+            },
+        }
+    }
+
+    /// 深さ `depth` の関数から見た名前を解決する. グローバル変数であれば `None` を返す.
+    /// 外側の関数の変数であれば, 途中の関数すべてに自由変数として登録する.
+    fn resolve_in(&mut self, depth: usize, name: &str, span: Span) -> Result<Option<Symbol>, MonkeyError> {
+        // 内側の関数から参照される場合は `let` より前でも見える
+        let innermost = depth == self.scopes.len() - 1;
+        let local = self.scopes[depth].blocks.iter().rev()
+            .filter_map(|block| block.get(name))
+            .find(|(_, declared)| *declared || !innermost);
+        if let Some(&(index, _)) = local {
+            return Ok(Some(Symbol::Local(index)));
+        }
+        if depth == 0 {
+            return Ok(None);
+        }
+        if let Some(symbol) = self.scopes[depth].symbols.get(name) {
+            return Ok(Some(*symbol));
+        }
+
+        let capture = match self.resolve_in(depth - 1, name, span)? {
+            Some(Symbol::Local(index)) => Capture::Local(index),
+            Some(Symbol::Free(index))  => Capture::Free(index),
+            _ => return Ok(None),
+        };
+        let scope = &mut self.scopes[depth];
+        let index = scope.captures.len();
+        if index > u8::MAX as usize {
+            return Err(MonkeyError::CompileLimit{ what: "captured variables", span });
+        }
+        scope.captures.push(capture);
+        scope.free_names.push(name.to_owned());
+        scope.symbols.insert(name.to_owned(), Symbol::Free(index));
+        Ok(Some(Symbol::Free(index)))
+    }
+
+    /// 文の列をコンパイルする. 実行後には最後の文の値 (文がなければ null) がスタックに一つ残る.
+    fn compile_statements(&mut self, stmts: &[Statement]) -> Result<(), MonkeyError> {
+        if stmts.is_empty() {
+            self.emit(Opcode::Null, &[]);
+        }
+        for (i, stmt) in stmts.iter().enumerate() {
+            if i > 0 {
+                self.emit(Opcode::Pop, &[]);
+            }
+            self.compile_statement(stmt)?;
+        }
+        Ok(())
+    }
+
+    /// 文をコンパイルする. 実行後には文の値がスタックに一つ残る.
+    fn compile_statement(&mut self, stmt: &Statement) -> Result<(), MonkeyError> {
+        match &stmt.kind {
+            StatementKind::Expression(expr) => self.compile_expression(expr)?,
+            StatementKind::Block(stmts) => self.compile_block(stmts, stmt.span)?,
+            StatementKind::Return(expr) => {
+                self.compile_expression(expr)?;
+                self.emit(Opcode::ReturnValue, &[]);
+            },
+            StatementKind::While{condition, body} => {
+                let start = self.scope().instructions.len();
+                self.compile_expression(condition)?;
+                let exit = self.emit(Opcode::JumpNotTruthy, &[0]);
+
+                let temporaries = self.scope().temporaries;
+                self.scope().loops.push(Loop { start, temporaries, values: 0, breaks: Vec::new() });
+                self.compile_statement(body)?;
+                self.emit(Opcode::Pop, &[]);
+                self.emit(Opcode::Jump, &[start]);
+                let finished = self.scope().loops.pop().unwrap();
+
+                self.patch_jump(exit);
+                for pos in finished.breaks {
+                    self.patch_jump(pos);
+                }
+                self.emit(Opcode::Null, &[]);
+            },
+            StatementKind::For{ident, iterable, body} => {
+                self.compile_expression(iterable)?;
+                self.emit_at(Opcode::Iterate, &[], iterable.span);
+                self.scope().temporaries += 2;
+                let start = self.emit(Opcode::Next, &[0]);
+
+                // ループ変数は繰り返しごとに新しいセルに束縛する
+                let name = match &ident.kind {
+                    ExpressionKind::Ident(name) => name,
+                    _ => unreachable!(),
+                };
+                self.scope().blocks.push(HashMap::new());
+                let index = self.define_block_local(name, ident.span)?;
+                self.emit(Opcode::ResetLocals, &[index, 1]);
+                self.compile_binding(ident, true)?;
+
+                let temporaries = self.scope().temporaries;
+                self.scope().loops.push(Loop { start, temporaries, values: 2, breaks: Vec::new() });
+                self.compile_statement(body)?;
+                self.emit(Opcode::Pop, &[]);
+                self.emit(Opcode::Jump, &[start]);
+                let finished = self.scope().loops.pop().unwrap();
+                self.scope().blocks.pop();
+                self.release(2);
+
+                // Opcode::Next は配列と添字を取り除いてからここへジャンプする
+                self.patch_jump(start);
+                for pos in finished.breaks {
+                    self.patch_jump(pos);
+                }
+                self.emit(Opcode::Null, &[]);
+            },
+            // ジャンプするので, 文の値を残さなくても後続の命令には到達しない
+            StatementKind::Break | StatementKind::Continue => {
+                // 構文解析器がループの外の break と continue を拒否している
+                let scope = self.scope();
+                let innermost = scope.loops.last().unwrap();
+                let (start, discard) = (innermost.start, scope.temporaries - innermost.temporaries);
+                if let StatementKind::Continue = stmt.kind {
+                    for _ in 0..discard {
+                        self.emit(Opcode::Pop, &[]);
+                    }
+                    self.emit(Opcode::Jump, &[start]);
+                } else {
+                    for _ in 0..discard + innermost.values {
+                        self.emit(Opcode::Pop, &[]);
+                    }
+                    let pos = self.emit(Opcode::Jump, &[0]);
+                    self.scope().loops.last_mut().unwrap().breaks.push(pos);
+                }
+            },
+            StatementKind::Let{ident, value} | StatementKind::Const{ident, value} => {
+                self.compile_expression(value)?;
+                self.compile_binding(ident, matches!(stmt.kind, StatementKind::Let{..}))?;
+                self.emit(Opcode::Null, &[]);
+            },
+            StatementKind::Assign{ident, op, value} => {
+                let name = match &ident.kind {
+                    ExpressionKind::Ident(name) => name,
+                    _ => unreachable!(),
+                };
+                let symbol = self.resolve(name, ident.span)?;
+                if self.is_constant(self.scopes.len() - 1, symbol) {
+                    return Err(MonkeyError::AssignToConstant(name.to_owned(), ident.span));
+                }
+                match op {
+                    Some(op) => {
+                        // 代入先が束縛されていなければ, 値を読む前に AssignToUndeclared になる
+                        match symbol {
+                            Symbol::Global(index) => self.emit_at(Opcode::LoadGlobal, &[index], ident.span),
+                            Symbol::Local(index)  => self.emit_at(Opcode::LoadLocal, &[index], ident.span),
+                            Symbol::Free(index)   => self.emit_at(Opcode::LoadFree, &[index], ident.span),
+                        };
+                        self.scope().temporaries += 1;
+                        self.compile_expression(value)?;
+                        self.release(1);
+                        let index = INFIX_OPERATORS.iter().position(|o| o == op).unwrap();
+                        self.emit_at(Opcode::Infix, &[index], stmt.span);
+                    },
+                    None => self.compile_expression(value)?,
+                }
+                match symbol {
+                    Symbol::Global(index) => self.emit_at(Opcode::AssignGlobal, &[index], ident.span),
+                    Symbol::Local(index)  => self.emit_at(Opcode::AssignLocal, &[index], ident.span),
+                    Symbol::Free(index)   => self.emit_at(Opcode::AssignFree, &[index], ident.span),
+                };
+                self.emit(Opcode::Null, &[]);
+            },
+        }
+        // ジャンプ先は u16 で表すので, 一つの関数の命令列はその範囲に収める
+        if self.scope().instructions.len() > u16::MAX as usize {
+            return Err(MonkeyError::CompileLimit{ what: "instructions", span: stmt.span });
+        }
+        Ok(())
+    }
+
+    /// ブロックをコンパイルする. ブロックの局所変数を確保し, 実行時には新しいセルに置き換えてから文を実行する.
+    fn compile_block(&mut self, stmts: &[Statement], span: Span) -> Result<(), MonkeyError> {
+        let first = self.scope().local_names.len();
+        self.scope().blocks.push(HashMap::new());
+        for name in let_names(stmts) {
+            self.define_block_local(name, span)?;
+        }
+        let count = self.scope().local_names.len() - first;
+        if count > u8::MAX as usize {
+            return Err(MonkeyError::CompileLimit{ what: "local variables", span });
+        }
+        if count > 0 {
+            self.emit(Opcode::ResetLocals, &[first, count]);
+        }
+
+        self.compile_statements(stmts)?;
+        self.scope().blocks.pop();
+        Ok(())
+    }
+
+    /// スタックの先頭を取り出して名前 `ident` に束縛する命令を出力する. mutable が偽なら `const` の束縛にする.
+    fn compile_binding(&mut self, ident: &Expression, mutable: bool) -> Result<(), MonkeyError> {
+        let name = match &ident.kind {
+            ExpressionKind::Ident(name) => name,
+            _ => unreachable!(),
+        };
+        let symbol = if let Some((index, declared)) = self.scope().blocks.last_mut().and_then(|block| block.get_mut(name)) {
+            *declared = true;
+            Symbol::Local(*index)
+        } else if self.scopes.len() == 1 {
+            Symbol::Global(self.global(name))
+        } else {
+            Symbol::Local(self.define_local(name, ident.span)?)
+        };
+
+        if self.is_constant(self.scopes.len() - 1, symbol) {
+            return Err(MonkeyError::AssignToConstant(name.to_owned(), ident.span));
+        }
+        match symbol {
+            Symbol::Global(index) => {
+                if mutable {
+                    self.emit(Opcode::SetGlobal, &[index]);
+                } else {
+                    self.constant_globals.insert(index);
+                    self.emit(Opcode::SetConstGlobal, &[index]);
+                }
+            },
+            Symbol::Local(index) => {
+                if mutable {
+                    self.emit(Opcode::SetLocal, &[index]);
+                } else {
+                    self.scope().constant_locals.insert(index);
+                    self.emit(Opcode::SetConstLocal, &[index]);
+                }
+            },
+            Symbol::Free(_) => unreachable!(),
+        }
+        Ok(())
+    }
+
+    /// 深さ `depth` の関数から見た変数が `const` で束縛されているかどうか.
+    fn is_constant(&self, depth: usize, symbol: Symbol) -> bool {
+        match symbol {
+            Symbol::Global(index) => self.constant_globals.contains(&index),
+            Symbol::Local(index)  => self.scopes[depth].constant_locals.contains(&index),
+            Symbol::Free(index)   => match self.scopes[depth].captures[index] {
+                Capture::Local(index) => self.is_constant(depth - 1, Symbol::Local(index)),
+                Capture::Free(index)  => self.is_constant(depth - 1, Symbol::Free(index)),
+            },
+        }
+    }
+
+    fn compile_expression(&mut self, expr: &Expression) -> Result<(), MonkeyError> {
+        match &expr.kind {
+            ExpressionKind::Integer(value) => {
+                let index = self.add_constant(Constant::Object(Object::Integer(*value)), expr.span)?;
+                self.emit(Opcode::Constant, &[index]);
+            },
+            ExpressionKind::BigInteger(value) => {
+                let index = self.add_constant(Constant::Object(Object::BigInteger(value.clone())), expr.span)?;
+                self.emit(Opcode::Constant, &[index]);
+            },
+            ExpressionKind::Float(value) => {
+                let index = self.add_constant(Constant::Object(Object::Float(*value)), expr.span)?;
+                self.emit(Opcode::Constant, &[index]);
+            },
+            ExpressionKind::String(s) => {
+                let index = self.add_constant(Constant::Object(Object::String(s.to_owned())), expr.span)?;
+                self.emit(Opcode::Constant, &[index]);
+            },
+            ExpressionKind::Bool(true)  => { self.emit(Opcode::True, &[]); },
+            ExpressionKind::Bool(false) => { self.emit(Opcode::False, &[]); },
+            ExpressionKind::Prefix{op, right} => {
+                self.compile_expression(right)?;
+                let index = PREFIX_OPERATORS.iter().position(|o| o == op).unwrap();
+                self.emit_at(Opcode::Prefix, &[index], expr.span);
+            },
+            ExpressionKind::Infix{op, left, right} => {
+                self.compile_operand(left)?;
+                self.compile_expression(right)?;
+                self.release(1);
+                let index = INFIX_OPERATORS.iter().position(|o| o == op).unwrap();
+                self.emit_at(Opcode::Infix, &[index], expr.span);
+            },
+            ExpressionKind::Logical{op, left, right} => {
+                self.compile_expression(left)?;
+                let jump = match op {
+                    operator::Logical::And => self.emit(Opcode::JumpIfFalsy, &[0]),
+                    operator::Logical::Or  => self.emit(Opcode::JumpIfTruthy, &[0]),
+                };
+                self.compile_expression(right)?;
+                self.patch_jump(jump);
+            },
+            ExpressionKind::If{condition, consequence, alternative} => {
+                self.compile_expression(condition)?;
+                let jump_not_truthy = self.emit(Opcode::JumpNotTruthy, &[0]);
+                self.compile_statement(consequence)?;
+                let jump = self.emit(Opcode::Jump, &[0]);
+                self.patch_jump(jump_not_truthy);
+                match alternative {
+                    Some(alt) => self.compile_statement(alt)?,
+                    None      => { self.emit(Opcode::Null, &[]); },
+                }
+                self.patch_jump(jump);
+            },
+            ExpressionKind::Ident(name) => {
+                match self.resolve(name, expr.span)? {
+                    Symbol::Global(index) => self.emit_at(Opcode::GetGlobal, &[index], expr.span),
+                    Symbol::Local(index)  => self.emit_at(Opcode::GetLocal, &[index], expr.span),
+                    Symbol::Free(index)   => self.emit_at(Opcode::GetFree, &[index], expr.span),
+                };
+            },
+            ExpressionKind::Function{parameters, body} => {
+                self.scopes.push(Scope::default());
+                for param in parameters.iter() {
+                    if let ExpressionKind::Ident(name) = &param.kind {
+                        self.define_local(name, param.span)?;
+                    }
+                }
+                // 関数本体のブロックは引数と同じ有効範囲とする
+                let stmts = match &body.kind {
+                    StatementKind::Block(stmts) => stmts,
+                    _ => unreachable!(),
+                };
+                for name in let_names(stmts) {
+                    self.define_local(name, body.span)?;
+                }
+
+                self.compile_statements(stmts)?;
+                self.emit(Opcode::ReturnValue, &[]);
+
+                let scope = self.scopes.pop().unwrap();
+                let function = CompiledFunction {
+                    instructions: scope.instructions,
+                    spans: scope.spans,
+                    num_parameters: parameters.len(),
+                    local_names: scope.local_names,
+                    captures: scope.captures,
+                    free_names: scope.free_names,
+                    source: format!("{}", expr),
+                };
+                let index = self.add_constant(Constant::Function(Rc::new(function)), expr.span)?;
+                self.emit(Opcode::Closure, &[index]);
+            },
+            ExpressionKind::Call{function, arguments} => {
+                self.compile_operand(function)?;
+                for arg in arguments.iter() {
+                    self.compile_operand(arg)?;
+                }
+                self.release(arguments.len() + 1);
+                if arguments.len() > u8::MAX as usize {
+                    return Err(MonkeyError::CompileLimit{ what: "arguments", span: expr.span });
+                }
+                self.emit_at(Opcode::Call, &[arguments.len()], expr.span);
+            },
+            ExpressionKind::Array(elements) => {
+                for element in elements.iter() {
+                    self.compile_operand(element)?;
+                }
+                self.release(elements.len());
+                if elements.len() > u16::MAX as usize {
+                    return Err(MonkeyError::CompileLimit{ what: "array elements", span: expr.span });
+                }
+                self.emit(Opcode::Array, &[elements.len()]);
+            },
+            ExpressionKind::Hash(pairs) => {
+                for (key, value) in pairs.iter() {
+                    self.compile_operand(key)?;
+                    self.compile_operand(value)?;
+                }
+                self.release(pairs.len() * 2);
+                if pairs.len() > u16::MAX as usize {
+                    return Err(MonkeyError::CompileLimit{ what: "hash pairs", span: expr.span });
+                }
+                let pos = self.emit(Opcode::Hash, &[pairs.len()]);
+                let spans = pairs.iter().map(|(key, _)| (pos, key.span));
+                self.scope().spans.extend(spans);
+            },
+            ExpressionKind::Index{left, index} => {
+                self.compile_operand(left)?;
+                self.compile_expression(index)?;
+                self.release(1);
+                self.emit_at(Opcode::Index, &[], expr.span);
+            },
+        }
+        Ok(())
+    }
+}
+
+/// 文の列の直下で `let` または `const` される名前を返す. 入れ子のブロックや関数リテラルの中には立ち入らない.
+fn let_names(stmts: &[Statement]) -> impl Iterator<Item = &str> {
+    stmts.iter().filter_map(|stmt| match &stmt.kind {
+        StatementKind::Let{ident, ..} | StatementKind::Const{ident, ..} => match &ident.kind {
+            ExpressionKind::Ident(name) => Some(name.as_str()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lexer::Lexer,
+        parser::Parser,
+        code::{make, Opcode, Disassembly},
+        compiler::{Compiler, Bytecode, Constant, Capture},
+        builtins,
+        error::MonkeyError,
+    };
+
+    fn compile(input: &str) -> Bytecode {
+        let program = Parser::new(Lexer::new(input)).parse_program().expect("Failed to parse!");
+        Compiler::new().compile(&program).expect("Failed to compile!")
+    }
+
+    #[test]
+    fn compile_infix_expression() {
+        let bytecode = compile("1 + 2; 3");
+        let expected: Vec<u8> = [
+            make(Opcode::Constant, &[0]),
+            make(Opcode::Constant, &[1]),
+            make(Opcode::Infix, &[0]),
+            make(Opcode::Pop, &[]),
+            make(Opcode::Constant, &[2]),
+        ].concat();
+        assert_eq!( format!("{}", Disassembly(&bytecode.main.instructions)), format!("{}", Disassembly(&expected)) );
+        assert_eq!( bytecode.constants.len(), 3 );
+    }
+
+    #[test]
+    fn compile_conditional() {
+        let bytecode = compile("if (true) { 10 }; 3333;");
+        let expected: Vec<u8> = [
+            make(Opcode::True, &[]),
+            make(Opcode::JumpNotTruthy, &[10]),
+            make(Opcode::Constant, &[0]),
+            make(Opcode::Jump, &[11]),
+            make(Opcode::Null, &[]),
+            make(Opcode::Pop, &[]),
+            make(Opcode::Constant, &[1]),
+        ].concat();
+        assert_eq!( format!("{}", Disassembly(&bytecode.main.instructions)), format!("{}", Disassembly(&expected)) );
+    }
+
+    #[test]
+    fn compile_logical() {
+        let bytecode = compile("true && false || true");
+        let expected: Vec<u8> = [
+            make(Opcode::True, &[]),
+            make(Opcode::JumpIfFalsy, &[5]),
+            make(Opcode::False, &[]),
+            make(Opcode::JumpIfTruthy, &[9]),
+            make(Opcode::True, &[]),
+        ].concat();
+        assert_eq!( format!("{}", Disassembly(&bytecode.main.instructions)), format!("{}", Disassembly(&expected)) );
+    }
+
+    #[test]
+    fn compile_while() {
+        let bytecode = compile("while (true) { if (false) { break; } continue; }");
+        let expected: Vec<u8> = [
+            make(Opcode::True, &[]),                // 0000
+            make(Opcode::JumpNotTruthy, &[23]),     // 0001
+            make(Opcode::False, &[]),               // 0004
+            make(Opcode::JumpNotTruthy, &[14]),     // 0005
+            make(Opcode::Jump, &[23]),              // 0008
+            make(Opcode::Jump, &[15]),              // 0011
+            make(Opcode::Null, &[]),                // 0014
+            make(Opcode::Pop, &[]),                 // 0015
+            make(Opcode::Jump, &[0]),               // 0016
+            make(Opcode::Pop, &[]),                 // 0019
+            make(Opcode::Jump, &[0]),               // 0020
+            make(Opcode::Null, &[]),                // 0023
+        ].concat();
+        assert_eq!( format!("{}", Disassembly(&bytecode.main.instructions)), format!("{}", Disassembly(&expected)) );
+
+        // 演算子の左辺などスタックに残っている値を捨ててからループを抜ける
+        let bytecode = compile("while (true) { 1 + if (true) { break; } }");
+        let expected: Vec<u8> = [
+            make(Opcode::True, &[]),                // 0000
+            make(Opcode::JumpNotTruthy, &[25]),     // 0001
+            make(Opcode::Constant, &[0]),           // 0004
+            make(Opcode::True, &[]),                // 0007
+            make(Opcode::JumpNotTruthy, &[18]),     // 0008
+            make(Opcode::Pop, &[]),                 // 0011
+            make(Opcode::Jump, &[25]),              // 0012
+            make(Opcode::Jump, &[19]),              // 0015
+            make(Opcode::Null, &[]),                // 0018
+            make(Opcode::Infix, &[0]),              // 0019
+            make(Opcode::Pop, &[]),                 // 0021
+            make(Opcode::Jump, &[0]),               // 0022
+            make(Opcode::Null, &[]),                // 0025
+        ].concat();
+        assert_eq!( format!("{}", Disassembly(&bytecode.main.instructions)), format!("{}", Disassembly(&expected)) );
+    }
+
+    #[test]
+    fn compile_for() {
+        let bytecode = compile("for (x in []) { if (x) { break; } continue; }");
+        let expected: Vec<u8> = [
+            make(Opcode::Array, &[0]),              // 0000
+            make(Opcode::Iterate, &[]),             // 0003
+            make(Opcode::Next, &[34]),              // 0004
+            make(Opcode::ResetLocals, &[0, 1]),     // 0007
+            make(Opcode::SetLocal, &[0]),           // 0010
+            make(Opcode::GetLocal, &[0]),           // 0012
+            make(Opcode::JumpNotTruthy, &[25]),     // 0014
+            make(Opcode::Pop, &[]),                 // 0017
+            make(Opcode::Pop, &[]),                 // 0018
+            make(Opcode::Jump, &[34]),              // 0019
+            make(Opcode::Jump, &[26]),              // 0022
+            make(Opcode::Null, &[]),                // 0025
+            make(Opcode::Pop, &[]),                 // 0026
+            make(Opcode::Jump, &[4]),               // 0027
+            make(Opcode::Pop, &[]),                 // 0030
+            make(Opcode::Jump, &[4]),               // 0031
+            make(Opcode::Null, &[]),                // 0034
+        ].concat();
+        assert_eq!( format!("{}", Disassembly(&bytecode.main.instructions)), format!("{}", Disassembly(&expected)) );
+        // トップレベルのループ変数も局所変数になる
+        assert_eq!( bytecode.main.local_names, vec!["x"] );
+    }
+
+    #[test]
+    fn compile_block_scope() {
+        let bytecode = compile("let x = 1; if (x) { let x = 2; let y = x; x = y; }");
+        let global = builtins::all().count();
+        let expected: Vec<u8> = [
+            make(Opcode::Constant, &[0]),           // 0000
+            make(Opcode::SetGlobal, &[global]),     // 0003
+            make(Opcode::Null, &[]),                // 0006
+            make(Opcode::Pop, &[]),                 // 0007
+            make(Opcode::GetGlobal, &[global]),     // 0008
+            make(Opcode::JumpNotTruthy, &[38]),     // 0011
+            make(Opcode::ResetLocals, &[0, 2]),     // 0014
+            make(Opcode::Constant, &[1]),           // 0017
+            make(Opcode::SetLocal, &[0]),           // 0020
+            make(Opcode::Null, &[]),                // 0022
+            make(Opcode::Pop, &[]),                 // 0023
+            make(Opcode::GetLocal, &[0]),           // 0024
+            make(Opcode::SetLocal, &[1]),           // 0026
+            make(Opcode::Null, &[]),                // 0028
+            make(Opcode::Pop, &[]),                 // 0029
+            make(Opcode::GetLocal, &[1]),           // 0030
+            make(Opcode::AssignLocal, &[0]),        // 0032
+            make(Opcode::Null, &[]),                // 0034
+            make(Opcode::Jump, &[39]),              // 0035
+            make(Opcode::Null, &[]),                // 0038
+        ].concat();
+        assert_eq!( format!("{}", Disassembly(&bytecode.main.instructions)), format!("{}", Disassembly(&expected)) );
+        assert_eq!( bytecode.main.local_names, vec!["x", "y"] );
+    }
+
+    #[test]
+    fn compile_assign() {
+        let bytecode = compile("let x = 1; x += 2; fn() { x = 3; let y = 4; y *= 5 }");
+        let global = builtins::all().count();
+        let expected: Vec<u8> = [
+            make(Opcode::Constant, &[0]),
+            make(Opcode::SetGlobal, &[global]),
+            make(Opcode::Null, &[]),
+            make(Opcode::Pop, &[]),
+            make(Opcode::LoadGlobal, &[global]),
+            make(Opcode::Constant, &[1]),
+            make(Opcode::Infix, &[0]),
+            make(Opcode::AssignGlobal, &[global]),
+            make(Opcode::Null, &[]),
+            make(Opcode::Pop, &[]),
+            make(Opcode::Closure, &[5]),
+        ].concat();
+        assert_eq!( format!("{}", Disassembly(&bytecode.main.instructions)), format!("{}", Disassembly(&expected)) );
+
+        let function = match &bytecode.constants[5] {
+            Constant::Function(function) => function,
+            constant => panic!("expected function, but got {:?}", constant),
+        };
+        let expected: Vec<u8> = [
+            make(Opcode::Constant, &[2]),
+            make(Opcode::AssignGlobal, &[global]),
+            make(Opcode::Null, &[]),
+            make(Opcode::Pop, &[]),
+            make(Opcode::Constant, &[3]),
+            make(Opcode::SetLocal, &[0]),
+            make(Opcode::Null, &[]),
+            make(Opcode::Pop, &[]),
+            make(Opcode::LoadLocal, &[0]),
+            make(Opcode::Constant, &[4]),
+            make(Opcode::Infix, &[2]),
+            make(Opcode::AssignLocal, &[0]),
+            make(Opcode::Null, &[]),
+            make(Opcode::ReturnValue, &[]),
+        ].concat();
+        assert_eq!( format!("{}", Disassembly(&function.instructions)), format!("{}", Disassembly(&expected)) );
+        assert_eq!( function.local_names, vec!["y"] );
+    }
+
+    #[test]
+    fn compile_const() {
+        let bytecode = compile("const x = 1; x");
+        let global = builtins::all().count();
+        let expected: Vec<u8> = [
+            make(Opcode::Constant, &[0]),
+            make(Opcode::SetConstGlobal, &[global]),
+            make(Opcode::Null, &[]),
+            make(Opcode::Pop, &[]),
+            make(Opcode::GetGlobal, &[global]),
+        ].concat();
+        assert_eq!( format!("{}", Disassembly(&bytecode.main.instructions)), format!("{}", Disassembly(&expected)) );
+
+        // 実行されない位置でもコンパイル時にエラーになる
+        let tests = [
+            ("const x = 1; if (false) { x = 2; }", 27),
+            ("const x = 1; let x = 2;", 18),
+            ("fn() { const y = 1; let g = fn() { y += 1; }; }", 36),
+            ("let f = fn() { if (true) { const z = 1; const z = 2; } }", 47),
+        ];
+        for (input, column) in tests.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            match Compiler::new().compile(&program) {
+                Err(MonkeyError::AssignToConstant(_, span)) => assert_eq!( span.start.column, *column, "input: {}", input ),
+                result => panic!("expected AssignToConstant for {}, but got {:?}", input, result.map(|_| ())),
+            }
+        }
+
+        // 内側のブロックで隠すのはよい
+        for input in ["const x = 1; if (true) { const x = 2; let y = x; }", "let f = fn(a) { const a = 1; a }"].iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            assert!( Compiler::new().compile(&program).is_ok(), "input: {}", input );
+        }
+    }
+
+    #[test]
+    fn compile_global_let() {
+        let bytecode = compile("let one = 1; one;");
+        let global = builtins::all().count();
+        let expected: Vec<u8> = [
+            make(Opcode::Constant, &[0]),
+            make(Opcode::SetGlobal, &[global]),
+            make(Opcode::Null, &[]),
+            make(Opcode::Pop, &[]),
+            make(Opcode::GetGlobal, &[global]),
+        ].concat();
+        assert_eq!( format!("{}", Disassembly(&bytecode.main.instructions)), format!("{}", Disassembly(&expected)) );
+        assert_eq!( bytecode.global_names[global], "one" );
+    }
+
+    #[test]
+    fn compile_closure() {
+        let bytecode = compile("fn(a) { let f = fn() { a + g() }; let g = fn() { 1 }; f }");
+
+        let inner = match &bytecode.constants[0] {
+            Constant::Function(function) => function,
+            constant => panic!("expected function, but got {:?}", constant),
+        };
+        assert_eq!( inner.free_names, vec!["a", "g"] );
+        assert_eq!( inner.captures, vec![Capture::Local(0), Capture::Local(2)] );
+
+        let outer = match &bytecode.constants[3] {
+            Constant::Function(function) => function,
+            constant => panic!("expected function, but got {:?}", constant),
+        };
+        assert_eq!( outer.local_names, vec!["a", "f", "g"] );
+        assert_eq!( outer.num_parameters, 1 );
+        assert_eq!( outer.source, "fn(a){let f = fn(){(a+g());};let g = fn(){1;};f;}" );
+    }
+}
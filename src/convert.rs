@@ -0,0 +1,282 @@
+use std::{collections::HashMap, hash::Hash, convert::TryFrom};
+use crate::{
+    object::{Object, ObjectType, HashKey, HashTable},
+    bigint::BigInt,
+    error::MonkeyError,
+    span::Span,
+};
+
+/// Rust の値を Monkey のオブジェクトに変換する.
+pub trait IntoMonkey {
+    fn into_monkey(self) -> Object;
+}
+
+/// Monkey のオブジェクトを Rust の値に変換する.
+/// 型が合わなければ MonkeyError::InvalidConversion を, 整数が収まらなければ MonkeyError::OutOfRange を返す.
+pub trait FromMonkey: Sized {
+    fn from_monkey(obj: Object) -> Result<Self, MonkeyError>;
+}
+
+fn invalid_conversion(expected: ObjectType, obj: &Object) -> MonkeyError {
+    MonkeyError::InvalidConversion{ expected, got: obj.type_of(), span: Span::default() }
+}
+
+fn out_of_range(target: &'static str, obj: Object) -> MonkeyError {
+    MonkeyError::OutOfRange{ target, value: Box::new(obj), span: Span::default() }
+}
+
+impl IntoMonkey for Object {
+    fn into_monkey(self) -> Object { self }
+}
+impl FromMonkey for Object {
+    fn from_monkey(obj: Object) -> Result<Self, MonkeyError> { Ok(obj) }
+}
+
+impl IntoMonkey for () {
+    fn into_monkey(self) -> Object { Object::Null }
+}
+impl FromMonkey for () {
+    fn from_monkey(obj: Object) -> Result<Self, MonkeyError> {
+        match obj {
+            Object::Null => Ok(()),
+            obj          => Err(invalid_conversion(ObjectType::Null, &obj)),
+        }
+    }
+}
+
+impl IntoMonkey for i32 {
+    fn into_monkey(self) -> Object { Object::Integer(self.into()) }
+}
+/// i32 に収まらない整数は MonkeyError::OutOfRange になる.
+impl FromMonkey for i32 {
+    fn from_monkey(obj: Object) -> Result<Self, MonkeyError> {
+        match obj {
+            Object::Integer(value) => i32::try_from(value).map_err(|_| out_of_range("i32", obj)),
+            Object::BigInteger(_)  => Err(out_of_range("i32", obj)),
+            obj                    => Err(invalid_conversion(ObjectType::Integer, &obj)),
+        }
+    }
+}
+
+impl IntoMonkey for i64 {
+    fn into_monkey(self) -> Object { Object::Integer(self) }
+}
+/// i64 に収まらない整数は MonkeyError::OutOfRange になる.
+impl FromMonkey for i64 {
+    fn from_monkey(obj: Object) -> Result<Self, MonkeyError> {
+        match obj {
+            Object::Integer(value) => Ok(value),
+            Object::BigInteger(_)  => Err(out_of_range("i64", obj)),
+            obj                    => Err(invalid_conversion(ObjectType::Integer, &obj)),
+        }
+    }
+}
+
+impl IntoMonkey for BigInt {
+    fn into_monkey(self) -> Object { Object::integer(self) }
+}
+impl FromMonkey for BigInt {
+    fn from_monkey(obj: Object) -> Result<Self, MonkeyError> {
+        match obj {
+            Object::Integer(value)    => Ok(BigInt::from(value)),
+            Object::BigInteger(value) => Ok(value),
+            obj                       => Err(invalid_conversion(ObjectType::Integer, &obj)),
+        }
+    }
+}
+
+impl IntoMonkey for f64 {
+    fn into_monkey(self) -> Object { Object::Float(self) }
+}
+/// 整数も最も近い浮動小数点数に変換する.
+impl FromMonkey for f64 {
+    fn from_monkey(obj: Object) -> Result<Self, MonkeyError> {
+        match obj {
+            Object::Float(value)   => Ok(value),
+            Object::Integer(value) => Ok(value as f64),
+            Object::BigInteger(value) => Ok(value.to_f64()),
+            obj                    => Err(invalid_conversion(ObjectType::Float, &obj)),
+        }
+    }
+}
+
+impl IntoMonkey for bool {
+    fn into_monkey(self) -> Object { Object::Bool(self) }
+}
+impl FromMonkey for bool {
+    fn from_monkey(obj: Object) -> Result<Self, MonkeyError> {
+        match obj {
+            Object::Bool(value) => Ok(value),
+            obj                 => Err(invalid_conversion(ObjectType::Bool, &obj)),
+        }
+    }
+}
+
+impl IntoMonkey for String {
+    fn into_monkey(self) -> Object { Object::String(self) }
+}
+impl IntoMonkey for &str {
+    fn into_monkey(self) -> Object { Object::String(self.to_owned()) }
+}
+impl FromMonkey for String {
+    fn from_monkey(obj: Object) -> Result<Self, MonkeyError> {
+        match obj {
+            Object::String(s) => Ok(s),
+            obj               => Err(invalid_conversion(ObjectType::String, &obj)),
+        }
+    }
+}
+
+impl<T: IntoMonkey> IntoMonkey for Vec<T> {
+    fn into_monkey(self) -> Object {
+        Object::Array(self.into_iter().map(IntoMonkey::into_monkey).collect())
+    }
+}
+impl<T: FromMonkey> FromMonkey for Vec<T> {
+    fn from_monkey(obj: Object) -> Result<Self, MonkeyError> {
+        match obj {
+            Object::Array(elements) => elements.into_iter().map(T::from_monkey).collect(),
+            obj                     => Err(invalid_conversion(ObjectType::Array, &obj)),
+        }
+    }
+}
+
+/// `None` は null に, `Some(x)` は `x` を変換したものになる.
+impl<T: IntoMonkey> IntoMonkey for Option<T> {
+    fn into_monkey(self) -> Object {
+        match self {
+            Some(value) => value.into_monkey(),
+            None        => Object::Null,
+        }
+    }
+}
+impl<T: FromMonkey> FromMonkey for Option<T> {
+    fn from_monkey(obj: Object) -> Result<Self, MonkeyError> {
+        match obj {
+            Object::Null => Ok(None),
+            obj          => T::from_monkey(obj).map(Some),
+        }
+    }
+}
+
+/// キーはハッシュ可能な型 (HashKey に変換できる型) に限る.
+impl<K: Into<HashKey>, V: IntoMonkey> IntoMonkey for HashMap<K, V> {
+    fn into_monkey(self) -> Object {
+        let mut table = HashTable::new();
+        for (key, value) in self.into_iter() {
+            table.insert(key.into(), value.into_monkey());
+        }
+        Object::Hash(table)
+    }
+}
+impl<K: FromMonkey + Eq + Hash, V: FromMonkey> FromMonkey for HashMap<K, V> {
+    fn from_monkey(obj: Object) -> Result<Self, MonkeyError> {
+        match obj {
+            Object::Hash(table) => table.iter()
+                .map(|(key, value)| Ok((K::from_monkey(key.to_object())?, V::from_monkey(value.clone())?)))
+                .collect(),
+            obj => Err(invalid_conversion(ObjectType::Hash, &obj)),
+        }
+    }
+}
+
+impl From<i32> for HashKey {
+    fn from(value: i32) -> Self { HashKey::Integer(value.into()) }
+}
+impl From<i64> for HashKey {
+    fn from(value: i64) -> Self { HashKey::Integer(value) }
+}
+impl From<bool> for HashKey {
+    fn from(value: bool) -> Self { HashKey::Bool(value) }
+}
+impl From<String> for HashKey {
+    fn from(value: String) -> Self { HashKey::String(value) }
+}
+impl From<&str> for HashKey {
+    fn from(value: &str) -> Self { HashKey::String(value.to_owned()) }
+}
+
+/// Monkey の関数に渡す引数の列に変換できる型. 要素が IntoMonkey であるタプルと Vec<Object> が実装する.
+pub trait IntoArgs {
+    fn into_args(self) -> Vec<Object>;
+}
+impl IntoArgs for Vec<Object> {
+    fn into_args(self) -> Vec<Object> { self }
+}
+impl IntoArgs for () {
+    fn into_args(self) -> Vec<Object> { Vec::new() }
+}
+
+macro_rules! impl_into_args {
+    ( $( $t:ident ),+ ) => {
+        impl<$( $t: IntoMonkey ),+> IntoArgs for ( $( $t, )+ ) {
+            #[allow(non_snake_case)]
+            fn into_args(self) -> Vec<Object> {
+                let ( $( $t, )+ ) = self;
+                vec![ $( $t.into_monkey() ),+ ]
+            }
+        }
+    };
+}
+impl_into_args!(A);
+impl_into_args!(A, B);
+impl_into_args!(A, B, C);
+impl_into_args!(A, B, C, D);
+impl_into_args!(A, B, C, D, E);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::{
+        object::Object,
+        convert::{IntoMonkey, FromMonkey},
+        error::MonkeyError,
+        bigint::BigInt,
+    };
+
+    fn round_trip<T: IntoMonkey + FromMonkey + Clone + PartialEq + std::fmt::Debug>(value: T) {
+        assert_eq!( T::from_monkey(value.clone().into_monkey()).unwrap(), value );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(42);
+        round_trip(true);
+        round_trip(1.5);
+        round_trip("monkey".to_owned());
+        round_trip(vec![1, 2, 3]);
+        round_trip(vec![Some("a".to_owned()), None]);
+        round_trip(Some(vec![true]));
+        round_trip(None::<i32>);
+
+        let mut map = HashMap::new();
+        map.insert("one".to_owned(), vec![1]);
+        map.insert("two".to_owned(), vec![1, 1]);
+        round_trip(map);
+    }
+
+    #[test]
+    fn test_invalid_conversion() {
+        match i32::from_monkey(Object::Bool(true)) {
+            Err(MonkeyError::InvalidConversion{..}) => {},
+            result => panic!("expected InvalidConversion, but got {:?}", result),
+        }
+        assert!( Vec::<i32>::from_monkey(vec![Object::Integer(1), Object::Null].into_monkey()).is_err() );
+        assert!( HashMap::<i32, bool>::from_monkey(Object::Array(vec![])).is_err() );
+        assert_eq!( f64::from_monkey(Object::Integer(2)).unwrap(), 2.0 );
+
+        // 多倍長整数に昇格した値
+        let big = Object::integer(BigInt::from_str_radix("9223372036854775808", 10).unwrap());
+        for result in [i64::from_monkey(big.clone()).map(|_| ()), i32::from_monkey(big.clone()).map(|_| ())].iter() {
+            match result {
+                Err(e @ MonkeyError::OutOfRange{..}) => assert!( format!("{}", e).starts_with("integer 9223372036854775808 is out of range") ),
+                result => panic!("expected OutOfRange, but got {:?}", result),
+            }
+        }
+        match i32::from_monkey(Object::Integer(1 << 40)) {
+            Err(MonkeyError::OutOfRange{target: "i32", ..}) => {},
+            result => panic!("expected OutOfRange, but got {:?}", result),
+        }
+        assert_eq!( f64::from_monkey(big).unwrap(), 9223372036854775808.0 );
+    }
+}
@@ -0,0 +1,323 @@
+use std::fmt;
+use crate::{
+    token::Token,
+    error::{MonkeyError, LexError},
+};
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::FloatOverflow(literal) => write!(f, "float literal is too large: {}", literal),
+            LexError::MalformedFloat(literal) => write!(f, "malformed float literal: {}", literal),
+            LexError::MissingDigits(literal) => write!(f, "integer literal has no digits: {}", literal),
+            LexError::InvalidDigit{literal, digit, radix} => {
+                write!(f, "invalid digit `{}` in {} literal: {}", digit, radix_name(*radix), literal)
+            },
+            LexError::MisplacedSeparator(literal) => write!(f, "misplaced digit separator: {}", literal),
+            LexError::UnterminatedString => write!(f, "unterminated string literal"),
+            LexError::InvalidEscape(seq) => write!(f, "unknown escape sequence: {}", seq),
+            LexError::UnterminatedComment => write!(f, "unterminated block comment"),
+        }
+    }
+}
+
+fn radix_name(radix: u32) -> &'static str {
+    match radix {
+        2  => "binary",
+        8  => "octal",
+        16 => "hexadecimal",
+        _  => "decimal",
+    }
+}
+
+impl fmt::Display for MonkeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MonkeyError::NotFoundPrefixTreatment(_) => write!(f, "expected an expression"),
+            MonkeyError::Lexical(error, _) => write!(f, "{}", error),
+            MonkeyError::InvalidToken(token, _) => write!(f, "unexpected {}", token),
+            MonkeyError::UnexpectedToken{expected, got, ..} => write!(f, "expected {}, found {}", expected, got),
+            MonkeyError::OutsideLoop(keyword, _) => write!(f, "{} outside of a loop", keyword),
+            MonkeyError::TypeMismatch(left, op, right, _) => write!(f, "type mismatch: {} {} {}", left, op, right),
+            MonkeyError::UnknownOperator(left, op, right, _) => write!(f, "unknown operator: {} {} {}", left, op, right),
+            MonkeyError::UnknownPrefixOperator(op, right, _) => write!(f, "unknown operator: {}{}", op, right),
+            MonkeyError::DivisionByZero(left, op, _) => write!(f, "division by zero: {} {} 0", left, op),
+            MonkeyError::IdentifierNotFound(ident, _) => write!(f, "identifier not found: {}", ident),
+            MonkeyError::AssignToUndeclared(ident, _) => write!(f, "cannot assign to undeclared variable: {}", ident),
+            MonkeyError::AssignToConstant(ident, _) => write!(f, "cannot assign to constant: {}", ident),
+            MonkeyError::IncorrectNumberOfArgs{expected, got, ..} => {
+                write!(f, "wrong number of arguments: expected {}, got {}", expected, got)
+            },
+            MonkeyError::IncorrectNumberOfArgsRange{min, max, got, ..} => {
+                write!(f, "wrong number of arguments: expected {} to {}, got {}", min, max, got)
+            },
+            MonkeyError::NotCallable(callee, _) => write!(f, "{} is not callable", callee),
+            MonkeyError::NotIterable(value, _) => write!(f, "{} is not iterable", value),
+            MonkeyError::IndexOutOfBounds{index, length, ..} => {
+                write!(f, "index out of bounds: the length is {} but the index is {}", length, index)
+            },
+            MonkeyError::IndexNotSupported(left, index, _) => write!(f, "cannot index {} with {}", left, index),
+            MonkeyError::InvalidArgument{function, got, ..} => {
+                write!(f, "argument to `{}` not supported, got {}", function, got)
+            },
+            MonkeyError::UnhashableKey(key, _) => write!(f, "unusable as hash key: {}", key),
+            MonkeyError::InvalidConversion{expected, got, ..} => write!(f, "cannot convert {} to {}", got, expected),
+            MonkeyError::OutOfRange{target, value, ..} => write!(f, "integer {} is out of range for `{}`", value, target),
+            MonkeyError::CompileLimit{what, ..} => write!(f, "too many {} to compile", what),
+            MonkeyError::NegativeShift(amount, _) => write!(f, "negative shift amount: {}", amount),
+            MonkeyError::TooLarge{what, ..} => write!(f, "the resulting {} is too large", what),
+            MonkeyError::ZeroStep(_) => write!(f, "the step of `range` must not be zero"),
+            MonkeyError::Host(error, _) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for MonkeyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MonkeyError::Host(error, _) => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+impl MonkeyError {
+    /// エラーの修正方法を示す短いヒントを返す.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            MonkeyError::NotFoundPrefixTreatment(_) => None,
+            MonkeyError::Lexical(error, _) => match error {
+                LexError::FloatOverflow(_) => Some(format!("floats must be at most {:e}", f64::MAX)),
+                LexError::MalformedFloat(_) => Some("an exponent needs at least one digit, as in `1e9`".to_owned()),
+                LexError::MissingDigits(_) => Some("add digits after the prefix, as in `0xff`, `0o755` or `0b1010`".to_owned()),
+                LexError::InvalidDigit{radix, ..} => Some(match radix {
+                    2 => "binary literals may only contain `0` and `1`".to_owned(),
+                    8 => "octal literals may only contain `0` to `7`".to_owned(),
+                    _ => "hexadecimal literals may only contain `0` to `9` and `a` to `f`".to_owned(),
+                }),
+                LexError::MisplacedSeparator(_) => Some("`_` may only appear between digits, as in `1_000`".to_owned()),
+                LexError::UnterminatedString => Some("the string starts here; add a closing `\"`".to_owned()),
+                LexError::InvalidEscape(_) => {
+                    Some(r#"valid escapes are \", \\, \n, \t, \r, \0 and \u{...}"#.to_owned())
+                },
+                LexError::UnterminatedComment => {
+                    Some("the comment starts here; add a closing `*/` (block comments nest)".to_owned())
+                },
+            },
+            MonkeyError::InvalidToken(Token::EOF, _) => Some("the input ends before the expression is complete".to_owned()),
+            MonkeyError::InvalidToken(_, _) => Some("this token cannot start an expression".to_owned()),
+            MonkeyError::UnexpectedToken{expected, ..} => match expected {
+                Token::Semicolon => Some("add `;` to end the statement".to_owned()),
+                Token::Assign    => Some("a binding needs `=` followed by a value".to_owned()),
+                Token::RParen    => Some("expected `)` to close the parenthesis".to_owned()),
+                Token::RBracket  => Some("expected `]` to close the brackets".to_owned()),
+                Token::LParen    => Some("expected `(` here".to_owned()),
+                Token::LBrace    => Some("a block must start with `{`".to_owned()),
+                Token::Ident(_)  => Some("expected a name to bind".to_owned()),
+                _                => None,
+            },
+            MonkeyError::OutsideLoop(..) => Some("`break` and `continue` must be inside a `while` or `for` body in the same function".to_owned()),
+            MonkeyError::TypeMismatch(_, op, _, _) => Some(format!("both operands of `{}` must have the same type", op)),
+            MonkeyError::UnknownOperator(left, op, _, _) => Some(format!("`{}` is not defined for {} values", op, left)),
+            MonkeyError::UnknownPrefixOperator(op, right, _) => Some(format!("`{}` is not defined for {} values", op, right)),
+            MonkeyError::DivisionByZero(..) => Some("check that the divisor is not zero".to_owned()),
+            MonkeyError::IdentifierNotFound(ident, _) => Some(format!("bind `{}` with `let` before using it", ident)),
+            MonkeyError::AssignToUndeclared(ident, _) => Some(format!("declare `{}` with `let` before assigning to it", ident)),
+            MonkeyError::AssignToConstant(ident, _) => Some(format!("`{}` is declared with `const`; use `let` if it needs to change", ident)),
+            MonkeyError::IncorrectNumberOfArgs{expected, ..} => Some(format!(
+                "this function takes {} argument{}", expected, if *expected == 1 { "" } else { "s" }
+            )),
+            MonkeyError::IncorrectNumberOfArgsRange{min, max, ..} => {
+                Some(format!("this function takes {} to {} arguments", min, max))
+            },
+            MonkeyError::NotCallable(_, _) => Some("only functions and builtin functions can be called".to_owned()),
+            MonkeyError::NotIterable(_, _) => Some("`for` iterates over arrays, strings and hashes".to_owned()),
+            MonkeyError::IndexOutOfBounds{length, ..} => Some(match length {
+                0 => "the array is empty".to_owned(),
+                _ => format!("valid indices are 0 to {}", length - 1),
+            }),
+            MonkeyError::IndexNotSupported(_, _, _) => {
+                Some("only arrays (by integers) and hashes can be indexed".to_owned())
+            },
+            MonkeyError::InvalidArgument{..} => None,
+            MonkeyError::UnhashableKey(_, _) => Some("only strings, integers and booleans can be hash keys".to_owned()),
+            MonkeyError::InvalidConversion{..} => None,
+            MonkeyError::OutOfRange{..} => None,
+            MonkeyError::CompileLimit{..} => Some("split this into smaller functions".to_owned()),
+            MonkeyError::NegativeShift(..) => Some("shift amounts must be zero or positive".to_owned()),
+            MonkeyError::TooLarge{..} => None,
+            MonkeyError::ZeroStep(_) => Some("use a positive step to count up or a negative step to count down".to_owned()),
+            MonkeyError::Host(_, _) => None,
+        }
+    }
+}
+
+/// ソースコードとともにエラーを rustc 風に表示するためのラッパー.
+///
+/// ```text
+/// error: expected `;`, found `}`
+///  --> script.monkey:2:10
+///   |
+/// 2 | return x }
+///   |          ^ add `;` to end the statement
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Diagnostic<'a> {
+    error: &'a MonkeyError,
+    source: &'a str,
+    path: Option<&'a str>,
+}
+impl<'a> Diagnostic<'a> {
+    pub fn new(error: &'a MonkeyError, source: &'a str) -> Self {
+        Diagnostic { error, source, path: None }
+    }
+
+    /// 表示に用いるファイル名を設定する.
+    pub fn with_path(mut self, path: &'a str) -> Self {
+        self.path = Some(path);
+        self
+    }
+}
+impl<'a> fmt::Display for Diagnostic<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "error: {}", self.error)?;
+
+        let span = self.error.span();
+        let hint = self.error.hint();
+
+        // ソースコード上の位置を持たないエラーはヒントだけを添える
+        if span.is_dummy() {
+            if let Some(hint) = hint {
+                writeln!(f, "  = help: {}", hint)?;
+            }
+            return Ok(());
+        }
+        // 末尾の改行の後 (入力の終わり) を指す場合は空の行として表示する
+        let line = self.source.lines().nth(span.start.line - 1).unwrap_or("");
+
+        let number = span.start.line.to_string();
+        let gutter = " ".repeat(number.len());
+        match self.path {
+            Some(path) => writeln!(f, "{}--> {}:{}", gutter, path, span.start)?,
+            None       => writeln!(f, "{}--> {}", gutter, span.start)?,
+        }
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", number, line)?;
+
+        // 複数行にまたがる範囲は先頭行の末尾まで下線を引く
+        let width = if span.end.line == span.start.line {
+            span.end.column.saturating_sub(span.start.column)
+        } else {
+            (line.chars().count() + 1).saturating_sub(span.start.column)
+        }.max(1);
+        let padding = " ".repeat(span.start.column - 1);
+        match hint {
+            Some(hint) => writeln!(f, "{} | {}{} {}", gutter, padding, "^".repeat(width), hint),
+            None       => writeln!(f, "{} | {}{}", gutter, padding, "^".repeat(width)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lexer::Lexer,
+        parser::Parser,
+        eval::Evaluator,
+        diagnostics::Diagnostic,
+    };
+
+    #[test]
+    fn render_parse_error() {
+        let input = "let x = 1;\nreturn x }";
+        let mut p = Parser::new(Lexer::new(input));
+        let err = p.parse_program().unwrap_err();
+
+        let expected = "\
+error: expected `;`, found `}`
+ --> 2:10
+  |
+2 | return x }
+  |          ^ add `;` to end the statement
+";
+        assert_eq!( format!("{}", Diagnostic::new(&err, input)), expected );
+    }
+
+    #[test]
+    fn render_end_of_input() {
+        let input = "let x = (1 +\n";
+        let mut p = Parser::new(Lexer::new(input));
+        let err = p.parse_program().unwrap_err();
+
+        let expected = "\
+error: unexpected end of input
+ --> 2:1
+  |
+2 | 
+  | ^ the input ends before the expression is complete
+";
+        assert_eq!( format!("{}", Diagnostic::new(&err, input)), expected );
+    }
+
+    #[test]
+    fn render_hints_outside_let_and_return() {
+        let tests = [
+            ("const x 1;", "a binding needs `=` followed by a value"),
+            ("let f = fn() { return 1 }", "add `;` to end the statement"),
+        ];
+        for (input, hint) in tests.iter() {
+            let err = Parser::new(Lexer::new(input)).parse_program().unwrap_err();
+            let rendered = format!("{}", Diagnostic::new(&err, input));
+            assert!( rendered.trim_end().ends_with(hint), "{}", rendered );
+        }
+    }
+
+    #[test]
+    fn render_runtime_error() {
+        let input = "let f = fn(x) { x };\nf(1) + true;";
+        let mut p = Parser::new(Lexer::new(input));
+        let program = p.parse_program().unwrap();
+        let err = Evaluator::new().eval(&program).unwrap_err();
+
+        let expected = "\
+error: type mismatch: integer + boolean
+ --> script.monkey:2:1
+  |
+2 | f(1) + true;
+  | ^^^^^^^^^^^ both operands of `+` must have the same type
+";
+        assert_eq!( format!("{}", Diagnostic::new(&err, input).with_path("script.monkey")), expected );
+    }
+
+    #[test]
+    fn render_unterminated_string() {
+        let input = "let x = 1;\nlet s = \"abc;\nlet y = 2;";
+        let mut p = Parser::new(Lexer::new(input));
+        let err = p.parse_program().unwrap_err();
+
+        let expected = "\
+error: unterminated string literal
+ --> 2:9
+  |
+2 | let s = \"abc;
+  |         ^^^^^ the string starts here; add a closing `\"`
+";
+        assert_eq!( format!("{}", Diagnostic::new(&err, input)), expected );
+    }
+
+    #[test]
+    fn render_invalid_digit() {
+        let input = "let mask = 0b1021;";
+        let mut p = Parser::new(Lexer::new(input));
+        let err = p.parse_program().unwrap_err();
+
+        let expected = "\
+error: invalid digit `2` in binary literal: 0b1021
+ --> 1:12
+  |
+1 | let mask = 0b1021;
+  |            ^^^^^^ binary literals may only contain `0` and `1`
+";
+        assert_eq!( format!("{}", Diagnostic::new(&err, input)), expected );
+    }
+}
@@ -1,10 +1,26 @@
-use std::{collections::HashMap, rc::Rc, cell::RefCell};
+use std::{collections::HashMap, rc::Rc, cell::RefCell, fmt};
 use crate::object::Object;
 
 
+/// 環境に束縛された値. `const` で束縛された値は書き換えられない.
 #[derive(Debug, Clone, PartialEq)]
+struct Binding {
+    value: Object,
+    mutable: bool,
+}
+
+/// Environment::define と Environment::assign が失敗した理由.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingError {
+    /// どの環境にも束縛されていない
+    Undeclared,
+    /// `const` で束縛されている
+    Constant,
+}
+
+#[derive(Clone, PartialEq, Default)]
 pub struct Environment {
-    store: HashMap<String, Object>,
+    store: HashMap<String, Binding>,
     host: Option<Rc<RefCell<Environment>>>,
 }
 impl Environment {
@@ -18,7 +34,7 @@ impl Environment {
 
     pub fn get(&self, key: &str) -> Option<Object> {
         match self.store.get(key) {
-            Some(obj) => Some(obj.clone()),
+            Some(binding) => Some(binding.value.clone()),
             None => match &self.host {
                 Some(env) => env.borrow().get(key),
                 None      => None,
@@ -26,7 +42,46 @@ impl Environment {
         }
     }
 
+    /// 書き換え可能な値を束縛する. 既に束縛されていれば `const` であっても置き換える.
     pub fn set(&mut self, key: String, value: Object) {
-        self.store.insert(key, value);
+        self.store.insert(key, Binding { value, mutable: true });
+    }
+
+    /// この環境に値を束縛する (`let` と `const`).
+    /// この環境で既に `const` で束縛されている名前は束縛し直せない. 外側の環境の束縛は隠すだけなので構わない.
+    pub fn define(&mut self, key: String, value: Object, mutable: bool) -> Result<(), BindingError> {
+        if self.store.get(&key).is_some_and(|binding| !binding.mutable) {
+            return Err(BindingError::Constant);
+        }
+        self.store.insert(key, Binding { value, mutable });
+        Ok(())
+    }
+
+    /// 名前が束縛されている最も内側の環境で値を置き換える.
+    pub fn assign(&mut self, key: &str, value: Object) -> Result<(), BindingError> {
+        match self.store.get_mut(key) {
+            Some(binding) if binding.mutable => {
+                binding.value = value;
+                Ok(())
+            },
+            Some(_) => Err(BindingError::Constant),
+            None => match &self.host {
+                Some(env) => env.borrow_mut().assign(key, value),
+                None      => Err(BindingError::Undeclared),
+            },
+        }
+    }
+}
+
+/// 関数は定義された環境を参照し, 環境は関数を値として持つので, 環境をたどると循環しうる.
+/// そのため束縛されている名前だけを表示する.
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.store.keys().collect();
+        names.sort();
+        f.debug_struct("Environment")
+            .field("names", &names)
+            .field("host", &self.host.is_some())
+            .finish()
     }
 }
@@ -1,16 +1,159 @@
+use std::{rc::Rc, error::Error};
 use crate::{
     token::Token,
     operator,
-    object::ObjectType,
+    object::{Object, ObjectType},
+    span::Span,
 };
 
+/// 字句解析のエラー. crate::token::Token::Error として構文解析器に渡される.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexError {
+    /// f64 で表すと無限大になる浮動小数点数リテラル
+    FloatOverflow(String),
+    /// 数字を伴わない指数部を持つ浮動小数点数リテラル
+    MalformedFloat(String),
+    /// `0x` などの接頭辞の後に数字のない整数リテラル
+    MissingDigits(String),
+    /// 基数に合わない数字や英字を含む整数リテラル. digit は最初の不正な文字.
+    InvalidDigit{literal: String, digit: char, radix: u32},
+    /// 数字に挟まれていない区切り文字 `_` を含む数値リテラル
+    MisplacedSeparator(String),
+    /// 閉じる `"` のない文字列リテラル. 範囲は開く `"` から入力の終端まで.
+    UnterminatedString,
+    /// 不正なエスケープシーケンス
+    InvalidEscape(String),
+    /// 閉じる `*/` のないブロックコメント. 範囲は最も外側の `/*` から入力の終端まで.
+    UnterminatedComment,
+}
+
+/// インタプリタが報告するエラー. すべてのバリアントがエラーの発生箇所を示す Span を持つ.
 #[derive(Debug, Clone)]
 pub enum MonkeyError {
-    NotFoundPrefixTreatment,
-    InvalidToken(Token),
-    UnexpectedToken{expected: Token, got: Token},
-    TypeMismatch(ObjectType, operator::Infix, ObjectType),
-    UnknownOperator(ObjectType, operator::Infix, ObjectType),
-    IdentifierNotFound(String),
-    IncorrectNumberOfArgs{expected: usize, got: usize},
+    NotFoundPrefixTreatment(Span),
+    Lexical(LexError, Span),
+    InvalidToken(Token, Span),
+    UnexpectedToken{expected: Token, got: Token, span: Span},
+    /// ループの外にある break または continue
+    OutsideLoop(Token, Span),
+    TypeMismatch(ObjectType, operator::Infix, ObjectType, Span),
+    UnknownOperator(ObjectType, operator::Infix, ObjectType, Span),
+    UnknownPrefixOperator(operator::Prefix, ObjectType, Span),
+    DivisionByZero(Box<Object>, operator::Infix, Span),
+    IdentifierNotFound(String, Span),
+    /// どこにも束縛されていない名前への代入
+    AssignToUndeclared(String, Span),
+    /// `const` で束縛された名前への代入または再宣言
+    AssignToConstant(String, Span),
+    IncorrectNumberOfArgs{expected: usize, got: usize, span: Span},
+    /// 引数の数が min 以上 max 以下でない
+    IncorrectNumberOfArgsRange{min: usize, max: usize, got: usize, span: Span},
+    NotCallable(ObjectType, Span),
+    /// for 文で繰り返せない値
+    NotIterable(ObjectType, Span),
+    IndexOutOfBounds{index: Box<Object>, length: usize, span: Span},
+    IndexNotSupported(ObjectType, ObjectType, Span),
+    InvalidArgument{function: String, got: ObjectType, span: Span},
+    UnhashableKey(ObjectType, Span),
+    InvalidConversion{expected: ObjectType, got: ObjectType, span: Span},
+    /// Rust の整数型 `target` に収まらない整数を変換しようとした.
+    OutOfRange{target: &'static str, value: Box<Object>, span: Span},
+    CompileLimit{what: &'static str, span: Span},
+    /// `<<` または `>>` のシフト量が負である.
+    NegativeShift(Box<Object>, Span),
+    /// 演算の結果が大きすぎて表現できない.
+    TooLarge{what: &'static str, span: Span},
+    /// 組み込み関数 `range` に 0 の刻み幅が渡された.
+    ZeroStep(Span),
+    /// ホスト (Rust) 側の関数が返したエラー. 元のエラーは downcast_host で取り出せる.
+    Host(Rc<dyn Error>, Span),
+}
+impl MonkeyError {
+    /// エラーの発生箇所を返す.
+    pub fn span(&self) -> Span {
+        match self {
+            MonkeyError::NotFoundPrefixTreatment(span)     => *span,
+            MonkeyError::Lexical(_, span)                  => *span,
+            MonkeyError::InvalidToken(_, span)             => *span,
+            MonkeyError::UnexpectedToken{span, ..}         => *span,
+            MonkeyError::TypeMismatch(_, _, _, span)       => *span,
+            MonkeyError::UnknownOperator(_, _, _, span)    => *span,
+            MonkeyError::UnknownPrefixOperator(_, _, span) => *span,
+            MonkeyError::DivisionByZero(_, _, span)        => *span,
+            MonkeyError::IdentifierNotFound(_, span)       => *span,
+            MonkeyError::AssignToUndeclared(_, span)       => *span,
+            MonkeyError::AssignToConstant(_, span)         => *span,
+            MonkeyError::IncorrectNumberOfArgs{span, ..}   => *span,
+            MonkeyError::IncorrectNumberOfArgsRange{span, ..} => *span,
+            MonkeyError::NotCallable(_, span)              => *span,
+            MonkeyError::NotIterable(_, span)              => *span,
+            MonkeyError::IndexOutOfBounds{span, ..}        => *span,
+            MonkeyError::IndexNotSupported(_, _, span)     => *span,
+            MonkeyError::InvalidArgument{span, ..}         => *span,
+            MonkeyError::UnhashableKey(_, span)            => *span,
+            MonkeyError::InvalidConversion{span, ..}       => *span,
+            MonkeyError::OutOfRange{span, ..}              => *span,
+            MonkeyError::CompileLimit{span, ..}            => *span,
+            MonkeyError::NegativeShift(_, span)            => *span,
+            MonkeyError::OutsideLoop(_, span)              => *span,
+            MonkeyError::TooLarge{span, ..}                => *span,
+            MonkeyError::ZeroStep(span)                    => *span,
+            MonkeyError::Host(_, span)                     => *span,
+        }
+    }
+
+    fn span_mut(&mut self) -> &mut Span {
+        match self {
+            MonkeyError::NotFoundPrefixTreatment(span)     => span,
+            MonkeyError::Lexical(_, span)                  => span,
+            MonkeyError::InvalidToken(_, span)             => span,
+            MonkeyError::UnexpectedToken{span, ..}         => span,
+            MonkeyError::TypeMismatch(_, _, _, span)       => span,
+            MonkeyError::UnknownOperator(_, _, _, span)    => span,
+            MonkeyError::UnknownPrefixOperator(_, _, span) => span,
+            MonkeyError::DivisionByZero(_, _, span)        => span,
+            MonkeyError::IdentifierNotFound(_, span)       => span,
+            MonkeyError::AssignToUndeclared(_, span)       => span,
+            MonkeyError::AssignToConstant(_, span)         => span,
+            MonkeyError::IncorrectNumberOfArgs{span, ..}   => span,
+            MonkeyError::IncorrectNumberOfArgsRange{span, ..} => span,
+            MonkeyError::NotCallable(_, span)              => span,
+            MonkeyError::NotIterable(_, span)              => span,
+            MonkeyError::IndexOutOfBounds{span, ..}        => span,
+            MonkeyError::IndexNotSupported(_, _, span)     => span,
+            MonkeyError::InvalidArgument{span, ..}         => span,
+            MonkeyError::UnhashableKey(_, span)            => span,
+            MonkeyError::InvalidConversion{span, ..}       => span,
+            MonkeyError::OutOfRange{span, ..}              => span,
+            MonkeyError::CompileLimit{span, ..}            => span,
+            MonkeyError::NegativeShift(_, span)            => span,
+            MonkeyError::OutsideLoop(_, span)              => span,
+            MonkeyError::TooLarge{span, ..}                => span,
+            MonkeyError::ZeroStep(span)                    => span,
+            MonkeyError::Host(_, span)                     => span,
+        }
+    }
+
+    /// ホスト側の関数から任意のエラーを返すためのエラーを生成する.
+    /// 発生箇所は関数呼び出し式の位置が設定される.
+    pub fn host<E: Error + 'static>(error: E) -> Self {
+        MonkeyError::Host(Rc::new(error), Span::default())
+    }
+
+    /// ホスト側の関数が返したエラーが型 `E` であれば, それへの参照を返す.
+    pub fn downcast_host<E: Error + 'static>(&self) -> Option<&E> {
+        match self {
+            MonkeyError::Host(error, _) => error.downcast_ref(),
+            _ => None,
+        }
+    }
+
+    /// 発生箇所が未設定 (ダミー) のエラーに発生箇所を設定する.
+    /// 組み込み関数など, ソースコード上の位置を知らない処理が返したエラーに用いる.
+    pub fn with_span(mut self, span: Span) -> Self {
+        if self.span().is_dummy() {
+            *self.span_mut() = span;
+        }
+        self
+    }
 }
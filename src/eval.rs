@@ -1,19 +1,58 @@
-use std::{rc::Rc, cell::RefCell};
+use std::{rc::Rc, cell::RefCell, convert::TryFrom};
 use crate::{
-    ast,
+    ast::{self, ExpressionKind, StatementKind},
     operator,
-    object::{Object, ObjectType},
-    env::Environment,
+    object::{Object, ObjectType, HashKey, HashTable},
+    env::{Environment, BindingError},
     error::MonkeyError,
+    span::Span,
+    builtins::{self, Arity, Builtin},
+    bigint::BigInt,
+    convert::{FromMonkey, IntoArgs},
 };
 
+/// 評価を中断して呼び出し元へ巻き戻す理由.
+/// return 文, break 文, continue 文は式の途中に現れても, 値にならずに関数やループまで巻き戻される.
+#[derive(Debug)]
+enum Unwind {
+    Error(MonkeyError),
+    Return(Object),
+    Break,
+    Continue,
+}
+impl From<MonkeyError> for Unwind {
+    fn from(e: MonkeyError) -> Self {
+        Unwind::Error(e)
+    }
+}
+
+/// 関数本体やプログラム全体の評価結果から return 文の値を取り出す.
+/// 構文解析器がループの外の break 文と continue 文を拒否しているので, それらはここまで巻き戻らない.
+fn returned(result: Result<Object, Unwind>) -> Result<Object, MonkeyError> {
+    match result {
+        Ok(obj) | Err(Unwind::Return(obj)) => Ok(obj),
+        Err(Unwind::Error(e))              => Err(e),
+        Err(Unwind::Break) | Err(Unwind::Continue) => unreachable!(),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Evaluator {
     env: Rc<RefCell<Environment>>,
 }
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl Evaluator {
+    /// 組み込み関数を束縛した環境を持つ Evaluator を生成する.
+    /// 組み込み関数はグローバル環境の外側の環境に置かれるので, 同じ名前で束縛し直すことができる.
     pub fn new() -> Self {
-        Evaluator { env: Rc::new(RefCell::new(Environment::new())) }
+        let mut builtins = Environment::new();
+        builtins::register(&mut builtins);
+        let global = Environment::virtual_environment(Rc::new(RefCell::new(builtins)));
+        Evaluator { env: Rc::new(RefCell::new(global)) }
     }
 
     pub fn from(env: Environment) -> Self {
@@ -28,53 +67,156 @@ impl Evaluator {
         self.env.borrow_mut().set(key, value);
     }
 
-    pub fn eval(&mut self, program: &ast::Program) -> Result<Object, MonkeyError> {
-        let mut result = Object::Null;
+    /// Rust のクロージャを Monkey の関数としてグローバル環境に登録する.
+    /// 引数の数は検査しないので, 必要ならクロージャの中で確かめること.
+    ///
+    /// ```
+    /// use monkey_interpreter::{eval::Evaluator, object::Object};
+    ///
+    /// let mut env = Evaluator::new();
+    /// env.register_fn("sum", |args: &[Object]| {
+    ///     let mut sum = 0;
+    ///     for arg in args {
+    ///         if let Object::Integer(value) = arg { sum += value; }
+    ///     }
+    ///     Ok(Object::Integer(sum))
+    /// });
+    /// ```
+    pub fn register_fn<F>(&mut self, name: &str, func: F)
+        where F: Fn(&[Object]) -> Result<Object, MonkeyError> + 'static
+    {
+        self.register_fn_with_arity(name, Arity::Variadic, func);
+    }
 
-        for stmt in program.statements.iter() {
-            result = self.eval_statement(stmt)?;
+    /// 引数の数 `arity` を指定して Rust のクロージャを Monkey の関数として登録する.
+    /// 呼び出し時の引数の数が一致しなければクロージャは呼ばれず
+    /// MonkeyError::IncorrectNumberOfArgs が返る.
+    pub fn register_fn_with_arity<F>(&mut self, name: &str, arity: Arity, func: F)
+        where F: Fn(&[Object]) -> Result<Object, MonkeyError> + 'static
+    {
+        self.set(name.to_owned(), Object::Builtin(Builtin::new(name, arity, func)));
+    }
 
-            if let Object::ReturnValue(value) = result {
-                return Ok(*value);
-            }
-        }
-        Ok(result)
+    /// グローバル環境に束縛された Monkey の関数を Rust から呼び出す.
+    /// 引数は IntoMonkey を実装する値のタプルで渡し, 戻り値は FromMonkey で `R` に変換する.
+    ///
+    /// ```
+    /// use monkey_interpreter::{eval::Evaluator, lexer::Lexer, parser::Parser};
+    ///
+    /// let mut env = Evaluator::new();
+    /// let program = Parser::new(Lexer::new("let add = fn(x, y) { x + y };")).parse_program().unwrap();
+    /// env.eval(&program).unwrap();
+    ///
+    /// let sum: i64 = env.call("add", (1, 2)).unwrap();
+    /// assert_eq!(sum, 3);
+    /// ```
+    pub fn call<A: IntoArgs, R: FromMonkey>(&self, name: &str, args: A) -> Result<R, MonkeyError> {
+        let function = self.get(name)
+            .ok_or_else(|| MonkeyError::IdentifierNotFound(name.to_owned(), Span::default()))?;
+        R::from_monkey(call_function(&function, args.into_args())?)
+    }
+
+    pub fn eval(&mut self, program: &ast::Program) -> Result<Object, MonkeyError> {
+        returned(self.eval_statements(&program.statements))
     }
 
-    fn eval_statement(&mut self, stmt: &ast::Statement) -> Result<Object, MonkeyError> {
-        match stmt {
-            ast::Statement::Expression(expr) => self.eval_expression(expr),
-            ast::Statement::Block(statements) => self.eval_block_statement(statements),
-            ast::Statement::Return(expr) => {
+    fn eval_statement(&mut self, stmt: &ast::Statement) -> Result<Object, Unwind> {
+        match &stmt.kind {
+            StatementKind::Expression(expr) => self.eval_expression(expr),
+            StatementKind::Block(statements) => self.eval_block_statement(statements),
+            StatementKind::Assign{ident, op, value} => {
+                let name = match &ident.kind {
+                    ExpressionKind::Ident(name) => name,
+                    _ => unreachable!(),
+                };
+                let value = match op {
+                    Some(op) => {
+                        // 代入先が束縛されていなければ, 値を読む前に AssignToUndeclared になる
+                        let current = self.get(name)
+                            .ok_or_else(|| MonkeyError::AssignToUndeclared(name.to_owned(), ident.span))?;
+                        let value = self.eval_expression(value)?;
+                        eval_infix_expression(op, current, value, stmt.span)?
+                    },
+                    None => self.eval_expression(value)?,
+                };
+                match self.env.borrow_mut().assign(name, value) {
+                    Ok(()) => Ok(Object::Null),
+                    Err(BindingError::Undeclared) => Err(MonkeyError::AssignToUndeclared(name.to_owned(), ident.span).into()),
+                    Err(BindingError::Constant)   => Err(MonkeyError::AssignToConstant(name.to_owned(), ident.span).into()),
+                }
+            },
+            StatementKind::Return(expr) => {
                 let obj = self.eval_expression(expr)?;
-                Ok(Object::ReturnValue(Box::new(obj)))
+                Err(Unwind::Return(obj))
             },
-            ast::Statement::Let{ident, value} => {
-                if let ast::Expression::Ident(ident) = ident {
-                    let value = self.eval_expression(value)?;
-                    self.set(ident.to_owned(), value);
-                    Ok(Object::Null)
-                } else {
-                    unreachable!()
+            StatementKind::While{condition, body} => {
+                while self.eval_expression(condition)?.is_truthy() {
+                    match self.eval_statement(body) {
+                        Ok(_) | Err(Unwind::Continue) => {},
+                        Err(Unwind::Break) => break,
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(Object::Null)
+            },
+            StatementKind::For{ident, iterable, body} => {
+                let name = match &ident.kind {
+                    ExpressionKind::Ident(name) => name,
+                    _ => unreachable!(),
+                };
+                let items = iterate(self.eval_expression(iterable)?, iterable.span)?;
+                let outer = Rc::clone(&self.env);
+                for item in items {
+                    // ループ変数は繰り返しごとの新しい環境に束縛する
+                    let mut scope = Environment::virtual_environment(Rc::clone(&outer));
+                    scope.set(name.to_owned(), item);
+                    self.env = Rc::new(RefCell::new(scope));
+                    let result = self.eval_statement(body);
+                    self.env = Rc::clone(&outer);
+                    match result {
+                        Ok(_) | Err(Unwind::Continue) => {},
+                        Err(Unwind::Break) => break,
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(Object::Null)
+            },
+            StatementKind::Break    => Err(Unwind::Break),
+            StatementKind::Continue => Err(Unwind::Continue),
+            StatementKind::Let{ident, value} | StatementKind::Const{ident, value} => {
+                let name = match &ident.kind {
+                    ExpressionKind::Ident(name) => name,
+                    _ => unreachable!(),
+                };
+                let value = self.eval_expression(value)?;
+                let mutable = matches!(stmt.kind, StatementKind::Let{..});
+                match self.env.borrow_mut().define(name.to_owned(), value, mutable) {
+                    Ok(()) => Ok(Object::Null),
+                    Err(_) => Err(MonkeyError::AssignToConstant(name.to_owned(), ident.span).into()),
                 }
             },
         }
     }
 
-    fn eval_block_statement(&mut self, stmts: &[ast::Statement]) -> Result<Object, MonkeyError> {
+    /// ブロックを現在の環境を外側とする新しい環境で評価する. ブロックの中で束縛した名前はブロックを出ると消える.
+    fn eval_block_statement(&mut self, stmts: &[ast::Statement]) -> Result<Object, Unwind> {
+        let outer = Rc::clone(&self.env);
+        self.env = Rc::new(RefCell::new(Environment::virtual_environment(Rc::clone(&outer))));
+        let result = self.eval_statements(stmts);
+        self.env = outer;
+        result
+    }
+
+    /// 文の列を現在の環境で評価する.
+    fn eval_statements(&mut self, stmts: &[ast::Statement]) -> Result<Object, Unwind> {
         let mut result = Object::Null;
-    
         for stmt in stmts.iter() {
             result = self.eval_statement(stmt)?;
-    
-            if let Object::ReturnValue(_) = result {
-                return Ok(result);
-            }
         }
         Ok(result)
     }
     
-    fn eval_expressions(&mut self, exprs: &[ast::Expression]) -> Result<Vec<Object>, MonkeyError> {
+    fn eval_expressions(&mut self, exprs: &[ast::Expression]) -> Result<Vec<Object>, Unwind> {
         let mut result = Vec::new();
         for expr in exprs.iter() {
             result.push(self.eval_expression(expr)?);
@@ -82,21 +224,30 @@ impl Evaluator {
         Ok(result)
     }
 
-    fn eval_expression(&mut self, expr: &ast::Expression) -> Result<Object, MonkeyError> {
-        match expr {
-            ast::Expression::String(s)      => Ok(Object::String(s.to_owned())),
-            ast::Expression::Integer(value) => Ok(Object::Integer(*value)),
-            ast::Expression::Bool(value)    => Ok(Object::Bool(*value)),
-            ast::Expression::Prefix{op, right} => {
+    fn eval_expression(&mut self, expr: &ast::Expression) -> Result<Object, Unwind> {
+        match &expr.kind {
+            ExpressionKind::String(s)      => Ok(Object::String(s.to_owned())),
+            ExpressionKind::Integer(value) => Ok(Object::Integer(*value)),
+            ExpressionKind::BigInteger(value) => Ok(Object::BigInteger(value.clone())),
+            ExpressionKind::Float(value)   => Ok(Object::Float(*value)),
+            ExpressionKind::Bool(value)    => Ok(Object::Bool(*value)),
+            ExpressionKind::Prefix{op, right} => {
                 let right = self.eval_expression(right)?;
-                eval_prefix_expression(op, right)
+                Ok(eval_prefix_expression(op, right, expr.span)?)
             },
-            ast::Expression::Infix{op, left, right} => {
+            ExpressionKind::Infix{op, left, right} => {
                 let left = self.eval_expression(left)?;
                 let right = self.eval_expression(right)?;
-                eval_infix_expression(op, left, right)
+                Ok(eval_infix_expression(op, left, right, expr.span)?)
             },
-            ast::Expression::If{condition, consequence, alternative, ..} => {
+            ExpressionKind::Logical{op, left, right} => {
+                let left = self.eval_expression(left)?;
+                match (op, left.is_truthy()) {
+                    (operator::Logical::And, false) | (operator::Logical::Or, true) => Ok(left),
+                    _ => self.eval_expression(right),
+                }
+            },
+            ExpressionKind::If{condition, consequence, alternative, ..} => {
                 if self.eval_expression(condition)?.is_truthy() {
                     self.eval_statement(consequence)
                 } else {
@@ -106,32 +257,46 @@ impl Evaluator {
                     }
                 }
             },
-            ast::Expression::Ident(ident) => match self.get(ident) {
+            ExpressionKind::Ident(ident) => match self.get(ident) {
                 Some(value) => Ok(value.clone()),
-                None         => Err(MonkeyError::IdentifierNotFound(ident.to_owned())),
+                None         => Err(MonkeyError::IdentifierNotFound(ident.to_owned(), expr.span).into()),
             },
-            ast::Expression::Function{parameters, body} => {
-                let env = Rc::clone(&self.env);
-                Ok(Object::Function{parameters: parameters.clone(), body: *body.clone(), env: Environment::virtual_environment(env)})
+            ExpressionKind::Function{parameters, body} => {
+                Ok(Object::Function{parameters: parameters.clone(), body: body.clone(), env: Rc::clone(&self.env)})
             },
-            ast::Expression::Call{function, arguments} => {
+            ExpressionKind::Call{function, arguments} => {
+                let function = self.eval_expression(function)?;
                 let args = self.eval_expressions(arguments)?;
-                
-                if let ast::Expression::Ident(func) = &**function {
-                    if func == "puts" {
-                        println!("{}", args.iter().map(|arg| format!("{} ", arg)).collect::<String>());
-                        return Ok(Object::Null);
-                    }
+                Ok(apply_function(function, args, expr.span)?)
+            },
+            ExpressionKind::Array(elements) => {
+                Ok(Object::Array(self.eval_expressions(elements)?))
+            },
+            ExpressionKind::Hash(pairs) => {
+                let mut table = HashTable::new();
+                for (key, value) in pairs.iter() {
+                    let hash_key = self.eval_hash_key(key)?;
+                    let value = self.eval_expression(value)?;
+                    table.insert(hash_key, value);
                 }
-
-                let function = self.eval_expression(function)?;
-                apply_function(function, args)
+                Ok(Object::Hash(table))
+            },
+            ExpressionKind::Index{left, index} => {
+                let left = self.eval_expression(left)?;
+                let index = self.eval_expression(index)?;
+                Ok(eval_index_expression(left, index, expr.span)?)
             },
         }
     }
+
+    /// 式を評価してハッシュのキーに変換する.
+    fn eval_hash_key(&mut self, expr: &ast::Expression) -> Result<HashKey, Unwind> {
+        let key = self.eval_expression(expr)?;
+        Ok(HashKey::from_object(&key).map_err(|t| MonkeyError::UnhashableKey(t, expr.span))?)
+    }
 }
 
-fn eval_prefix_expression(op: &operator::Prefix, right: Object) -> Result<Object, MonkeyError> {
+pub(crate) fn eval_prefix_expression(op: &operator::Prefix, right: Object, span: Span) -> Result<Object, MonkeyError> {
     match op {
         operator::Prefix::Bang => match right {
             Object::Bool(value) => Ok(Object::Bool(!value)),
@@ -139,75 +304,270 @@ fn eval_prefix_expression(op: &operator::Prefix, right: Object) -> Result<Object
             _ => Ok(Object::Bool(false)),
         },
         operator::Prefix::Minus => match right {
-            Object::Integer(value) => Ok(Object::Integer(- value)),
-            _ => Ok(Object::Null),
+            // i64::MIN の符号を反転すると多倍長整数になる
+            Object::Integer(value) => Ok(match value.checked_neg() {
+                Some(value) => Object::Integer(value),
+                None        => Object::integer(-BigInt::from(value)),
+            }),
+            Object::BigInteger(value) => Ok(Object::integer(-value)),
+            Object::Float(value) => Ok(Object::Float(-value)),
+            right => Err(MonkeyError::UnknownPrefixOperator(*op, right.type_of(), span)),
         },
     }
 }
 
-fn eval_infix_expression(op: &operator::Infix, left: Object, right: Object) -> Result<Object, MonkeyError> {
+/// `*`, `**`, `<<` が返す整数のビット数の上限. 巨大な整数を不用意に作らないようにする.
+const MAX_INTEGER_BITS: u64 = 1 << 16;
+
+/// `*` による文字列の繰り返しで作る文字列のバイト数の上限.
+const MAX_STRING_LEN: usize = 1 << 28;
+
+/// 中置演算子を適用する. 整数同士の `/` と `%` は 0 方向に切り捨てる整数除算で, 0 による除算はエラーになる.
+/// どちらかが浮動小数点数であれば浮動小数点数の除算になる.
+pub(crate) fn eval_infix_expression(op: &operator::Infix, left: Object, right: Object, span: Span) -> Result<Object, MonkeyError> {
     match (left, right) {
-        (Object::Integer(left), Object::Integer(right)) => {
+        (Object::Integer(left), Object::Integer(right)) => eval_integer_infix_expression(op, left, right, span),
+        (Object::Integer(left), Object::BigInteger(right)) => {
+            eval_big_integer_infix_expression(op, BigInt::from(left), right, span)
+        },
+        (Object::BigInteger(left), Object::Integer(right)) => {
+            eval_big_integer_infix_expression(op, left, BigInt::from(right), span)
+        },
+        (Object::BigInteger(left), Object::BigInteger(right)) => eval_big_integer_infix_expression(op, left, right, span),
+        // 整数と浮動小数点数の演算では整数を浮動小数点数に変換する
+        (Object::Float(left), Object::Float(right)) => eval_float_infix_expression(op, left, right, span),
+        (Object::Integer(left), Object::Float(right)) => eval_float_infix_expression(op, left as f64, right, span),
+        (Object::BigInteger(left), Object::Float(right)) => eval_float_infix_expression(op, left.to_f64(), right, span),
+        (Object::Float(left), Object::Integer(right)) => eval_float_infix_expression(op, left, right as f64, span),
+        (Object::Float(left), Object::BigInteger(right)) => eval_float_infix_expression(op, left, right.to_f64(), span),
+        (Object::Bool(left), Object::Bool(right)) => {
             match op {
-                operator::Infix::Plus     => Ok(Object::Integer(left + right)),
-                operator::Infix::Minus    => Ok(Object::Integer(left - right)),
-                operator::Infix::Asterisk => Ok(Object::Integer(left * right)),
-                operator::Infix::Slash    => Ok(Object::Integer(left / right)),
-                operator::Infix::Eq       => Ok(Object::Bool(left == right)),
-                operator::Infix::NotEq    => Ok(Object::Bool(left != right)),
-                operator::Infix::LT       => Ok(Object::Bool(left < right)),
-                operator::Infix::GT       => Ok(Object::Bool(left > right)),
+                operator::Infix::Eq    => Ok(Object::Bool(left == right)),
+                operator::Infix::NotEq => Ok(Object::Bool(left != right)),
+                op  => Err(MonkeyError::UnknownOperator(ObjectType::Bool, *op, ObjectType::Bool, span)),
             }
         },
-        (Object::Bool(left), Object::Bool(right)) => {
+        (Object::String(left), Object::String(right)) => {
             match op {
+                operator::Infix::Plus  => Ok(Object::String(left + &right)),
                 operator::Infix::Eq    => Ok(Object::Bool(left == right)),
                 operator::Infix::NotEq => Ok(Object::Bool(left != right)),
-                op  => Err(MonkeyError::UnknownOperator(ObjectType::Bool, *op, ObjectType::Bool)),
+                operator::Infix::LT    => Ok(Object::Bool(left < right)),
+                operator::Infix::GT    => Ok(Object::Bool(left > right)),
+                operator::Infix::LTEq  => Ok(Object::Bool(left <= right)),
+                operator::Infix::GTEq  => Ok(Object::Bool(left >= right)),
+                op  => Err(MonkeyError::UnknownOperator(ObjectType::String, *op, ObjectType::String, span)),
             }
         },
-        (Object::Integer(_), Object::Bool(_)) => {
-            Err(MonkeyError::TypeMismatch(ObjectType::Integer, *op, ObjectType::Bool)) 
+        (Object::String(left), right @ Object::Integer(_)) | (Object::String(left), right @ Object::BigInteger(_))
+            if *op == operator::Infix::Asterisk => repeat_string(&left, &right, span),
+        (left, right) if left.type_of() != right.type_of() => {
+            Err(MonkeyError::TypeMismatch(left.type_of(), *op, right.type_of(), span))
+        },
+        (left, right) => Err(MonkeyError::UnknownOperator(left.type_of(), *op, right.type_of(), span)),
+    }
+}
+
+/// 浮動小数点数の演算は IEEE 754 に従う. 0 による除算はエラーではなく無限大か NaN になり,
+/// NaN はそれ自身を含むどの値とも等しくなく, 大小比較は常に偽になる.
+/// ビット演算とシフトは浮動小数点数には定義されない.
+fn eval_float_infix_expression(op: &operator::Infix, left: f64, right: f64, span: Span) -> Result<Object, MonkeyError> {
+    match op {
+        operator::Infix::Plus     => Ok(Object::Float(left + right)),
+        operator::Infix::Minus    => Ok(Object::Float(left - right)),
+        operator::Infix::Asterisk => Ok(Object::Float(left * right)),
+        operator::Infix::Slash    => Ok(Object::Float(left / right)),
+        operator::Infix::Percent  => Ok(Object::Float(left % right)),
+        operator::Infix::Power    => Ok(Object::Float(left.powf(right))),
+        operator::Infix::Eq       => Ok(Object::Bool(left == right)),
+        operator::Infix::NotEq    => Ok(Object::Bool(left != right)),
+        operator::Infix::LT       => Ok(Object::Bool(left < right)),
+        operator::Infix::GT       => Ok(Object::Bool(left > right)),
+        operator::Infix::LTEq     => Ok(Object::Bool(left <= right)),
+        operator::Infix::GTEq     => Ok(Object::Bool(left >= right)),
+        operator::Infix::BitAnd | operator::Infix::BitOr | operator::Infix::BitXor
+            | operator::Infix::Shl | operator::Infix::Shr => {
+            Err(MonkeyError::UnknownOperator(ObjectType::Float, *op, ObjectType::Float, span))
+        },
+    }
+}
+
+/// 整数同士の演算. 結果が i64 に収まらなければ多倍長整数で計算し直す.
+fn eval_integer_infix_expression(op: &operator::Infix, left: i64, right: i64, span: Span) -> Result<Object, MonkeyError> {
+    let result = match op {
+        operator::Infix::Plus     => left.checked_add(right),
+        operator::Infix::Minus    => left.checked_sub(right),
+        operator::Infix::Asterisk => left.checked_mul(right),
+        operator::Infix::Slash    => left.checked_div(right),
+        operator::Infix::Percent  => left.checked_rem(right),
+        operator::Infix::Power    => u32::try_from(right).ok().and_then(|exp| left.checked_pow(exp)),
+        operator::Infix::Eq       => return Ok(Object::Bool(left == right)),
+        operator::Infix::NotEq    => return Ok(Object::Bool(left != right)),
+        operator::Infix::LT       => return Ok(Object::Bool(left < right)),
+        operator::Infix::GT       => return Ok(Object::Bool(left > right)),
+        operator::Infix::LTEq     => return Ok(Object::Bool(left <= right)),
+        operator::Infix::GTEq     => return Ok(Object::Bool(left >= right)),
+        operator::Infix::BitAnd   => Some(left & right),
+        operator::Infix::BitOr    => Some(left | right),
+        operator::Infix::BitXor   => Some(left ^ right),
+        // 溢れたビットがあれば多倍長整数で計算する
+        operator::Infix::Shl      => u32::try_from(right).ok()
+            .and_then(|n| left.checked_shl(n).filter(|shifted| shifted >> n == left)),
+        operator::Infix::Shr      => u32::try_from(right).ok().map(|n| left >> n.min(63)),
+    };
+    match result {
+        Some(value) => Ok(Object::Integer(value)),
+        None        => eval_big_integer_infix_expression(op, BigInt::from(left), BigInt::from(right), span),
+    }
+}
+
+/// 多倍長整数の演算. 結果は i64 に収まれば Object::Integer になる.
+fn eval_big_integer_infix_expression(op: &operator::Infix, left: BigInt, right: BigInt, span: Span) -> Result<Object, MonkeyError> {
+    match op {
+        operator::Infix::Plus     => Ok(Object::integer(&left + &right)),
+        operator::Infix::Minus    => Ok(Object::integer(&left - &right)),
+        // 積のビット数は高々両辺のビット数の和
+        operator::Infix::Asterisk if left.bits() + right.bits() > MAX_INTEGER_BITS => {
+            Err(MonkeyError::TooLarge{ what: "integer", span })
+        },
+        operator::Infix::Asterisk => Ok(Object::integer(&left * &right)),
+        operator::Infix::Slash | operator::Infix::Percent => match left.div_rem(&right) {
+            Some((quotient, _)) if *op == operator::Infix::Slash => Ok(Object::integer(quotient)),
+            Some((_, remainder)) => Ok(Object::integer(remainder)),
+            None => Err(MonkeyError::DivisionByZero(Box::new(Object::integer(left)), *op, span)),
+        },
+        operator::Infix::Power    => eval_big_integer_power(left, right, span),
+        operator::Infix::Eq       => Ok(Object::Bool(left == right)),
+        operator::Infix::NotEq    => Ok(Object::Bool(left != right)),
+        operator::Infix::LT       => Ok(Object::Bool(left < right)),
+        operator::Infix::GT       => Ok(Object::Bool(left > right)),
+        operator::Infix::LTEq     => Ok(Object::Bool(left <= right)),
+        operator::Infix::GTEq     => Ok(Object::Bool(left >= right)),
+        operator::Infix::BitAnd   => Ok(Object::integer(&left & &right)),
+        operator::Infix::BitOr    => Ok(Object::integer(&left | &right)),
+        operator::Infix::BitXor   => Ok(Object::integer(&left ^ &right)),
+        operator::Infix::Shl | operator::Infix::Shr if right.is_negative() => {
+            Err(MonkeyError::NegativeShift(Box::new(Object::integer(right)), span))
         },
-        (Object::Bool(_), Object::Integer(_)) => {
-            Err(MonkeyError::TypeMismatch(ObjectType::Bool, *op, ObjectType::Integer)) 
+        // usize に収まらないシフト量は 0 以外を左シフトすれば必ず上限を超え, 右シフトすれば 0 か -1 になる
+        operator::Infix::Shl => match right.to_i64().and_then(|n| usize::try_from(n).ok()) {
+            _ if left.is_zero() => Ok(Object::Integer(0)),
+            Some(n) if left.bits() + n as u64 <= MAX_INTEGER_BITS => Ok(Object::integer(&left << n)),
+            _ => Err(MonkeyError::TooLarge{ what: "integer", span }),
+        },
+        operator::Infix::Shr => {
+            let n = right.to_i64().and_then(|n| usize::try_from(n).ok()).unwrap_or(usize::MAX);
+            Ok(Object::integer(&left >> n))
         },
-        _ => Ok(Object::Null)
     }
 }
 
-fn apply_function(function: Object, args: Vec<Object>) -> Result<Object, MonkeyError> {
-    if let Object::Function{parameters, body, env} = function {
-        if parameters.len() != args.len() {
-            // 関数の引数の数が与えられた expr の数に一致しなかったらエラーを返して終了.
-            return Err(MonkeyError::IncorrectNumberOfArgs{ expected: parameters.len(), got: args.len() });
-        }
+/// 整数の累乗. 指数が負なら浮動小数点数になる.
+fn eval_big_integer_power(base: BigInt, exp: BigInt, span: Span) -> Result<Object, MonkeyError> {
+    if exp.is_negative() {
+        return Ok(Object::Float(base.to_f64().powf(exp.to_f64())));
+    }
+    // 0, 1, -1 は何乗しても絶対値が変わらないので指数の偶奇だけを見る
+    if base.bits() <= 1 {
+        return Ok(Object::integer(base.pow(if exp.is_zero() { 0 } else if exp.is_odd() { 1 } else { 2 })));
+    }
+    match exp.to_i64().and_then(|exp| u32::try_from(exp).ok()) {
+        Some(exp) if base.bits() * exp as u64 <= MAX_INTEGER_BITS => Ok(Object::integer(base.pow(exp))),
+        _ => Err(MonkeyError::TooLarge{ what: "integer", span }),
+    }
+}
 
-        let mut env = Evaluator::from(env);
-        for (ident, arg) in parameters.iter().zip(args.iter()) {
-            if let ast::Expression::Ident(ident) = ident {
-                env.set(ident.to_owned(), arg.clone());
-            } 
-        }
-        match env.eval_statement(&body)? {
-            Object::ReturnValue(obj) => Ok(*obj),
-            obj => Ok(obj),
-        }
-    } else {
-        unreachable!()
+/// 文字列を count 回繰り返す. 回数が 0 以下なら空文字列になる.
+fn repeat_string(s: &str, count: &Object, span: Span) -> Result<Object, MonkeyError> {
+    let count = match count {
+        Object::Integer(count)                            => (*count).max(0),
+        Object::BigInteger(count) if count.is_negative() => 0,
+        _                                                 => i64::MAX,
+    };
+    usize::try_from(count).ok()
+        .filter(|count| s.len().checked_mul(*count).is_some_and(|len| len <= MAX_STRING_LEN))
+        .map(|count| Object::String(s.repeat(count)))
+        .ok_or(MonkeyError::TooLarge{ what: "string", span })
+}
+
+/// for 文が順に取り出す値を返す. 配列は要素, 文字列は 1 文字ずつの文字列, ハッシュはキーを挿入順に返す.
+pub(crate) fn iterate(obj: Object, span: Span) -> Result<Vec<Object>, MonkeyError> {
+    match obj {
+        Object::Array(elements) => Ok(elements),
+        Object::String(s)       => Ok(s.chars().map(|c| Object::String(c.to_string())).collect()),
+        Object::Hash(table)     => Ok(table.iter().map(|(key, _)| key.to_object()).collect()),
+        obj                     => Err(MonkeyError::NotIterable(obj.type_of(), span)),
     }
 }
 
+pub(crate) fn eval_index_expression(left: Object, index: Object, span: Span) -> Result<Object, MonkeyError> {
+    match (&left, &index) {
+        (Object::Array(elements), Object::Integer(_)) | (Object::Array(elements), Object::BigInteger(_)) => {
+            let element = match index {
+                Object::Integer(i) => usize::try_from(i).ok().and_then(|i| elements.get(i)),
+                _                  => None,
+            };
+            match element {
+                Some(element) => Ok(element.clone()),
+                None => Err(MonkeyError::IndexOutOfBounds{ index: Box::new(index.clone()), length: elements.len(), span }),
+            }
+        },
+        (Object::Hash(table), _) => {
+            let key = HashKey::from_object(&index).map_err(|t| MonkeyError::UnhashableKey(t, span))?;
+            Ok(table.get(&key).cloned().unwrap_or(Object::Null))
+        },
+        _ => Err(MonkeyError::IndexNotSupported(left.type_of(), index.type_of(), span)),
+    }
+}
+
+/// 関数オブジェクト (Monkey の関数または組み込み関数) を引数に適用する.
+/// ホスト側の関数が引数として受け取った関数を呼び出すのにも使える.
+/// 関数でないオブジェクトに対しては MonkeyError::NotCallable を返す.
+pub fn call_function(function: &Object, args: Vec<Object>) -> Result<Object, MonkeyError> {
+    apply_function(function.clone(), args, Span::default())
+}
+
+fn apply_function(function: Object, args: Vec<Object>, span: Span) -> Result<Object, MonkeyError> {
+    let (parameters, body, env) = match function {
+        Object::Function{parameters, body, env} => (parameters, body, env),
+        Object::Builtin(builtin) => return builtin.call(&args).map_err(|e| e.with_span(span)),
+        obj => return Err(MonkeyError::NotCallable(obj.type_of(), span)),
+    };
+
+    if parameters.len() != args.len() {
+        // 関数の引数の数が与えられた expr の数に一致しなかったらエラーを返して終了.
+        return Err(MonkeyError::IncorrectNumberOfArgs{ expected: parameters.len(), got: args.len(), span });
+    }
+
+    let mut env = Evaluator::from(Environment::virtual_environment(env));
+    for (ident, arg) in parameters.iter().zip(args.iter()) {
+        if let ExpressionKind::Ident(ident) = &ident.kind {
+            env.set(ident.to_owned(), arg.clone());
+        } 
+    }
+    // 関数本体のブロックは引数と同じ環境で評価する
+    returned(match &body.kind {
+        StatementKind::Block(stmts) => env.eval_statements(stmts),
+        _                           => env.eval_statement(&body),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
         ast,
         lexer::Lexer,
         parser::Parser,
-        object::Object,
+        object::{Object, ObjectType},
         eval::Evaluator,
+        error::MonkeyError,
+        bigint::BigInt,
     };
 
+    fn big(s: &str) -> BigInt {
+        BigInt::from_str_radix(s, 10).unwrap()
+    }
+
     #[test]
     fn eval_expression() {
         let probrem = [
@@ -226,6 +586,33 @@ mod tests {
             ("let x = 5;", Object::Null),
             ("let x = 5; x*2;", Object::Integer(10)),
             ("\"foo bar\"", Object::String("foo bar".to_owned())),
+            ("[1, 2 * 2, 3 + 3]", Object::Array(vec![Object::Integer(1), Object::Integer(4), Object::Integer(6)])),
+            ("[1, 2, 3][0]", Object::Integer(1)),
+            ("let i = 0; [1][i];", Object::Integer(1)),
+            ("[1, 2, 3][1 + 1];", Object::Integer(3)),
+            ("let a = [1, 2, 3]; a[0] + a[1] + a[2];", Object::Integer(6)),
+            ("len(\"\")", Object::Integer(0)),
+            ("len(\"four\")", Object::Integer(4)),
+            ("len([1, 2, 3])", Object::Integer(3)),
+            ("first([1, 2, 3])", Object::Integer(1)),
+            ("first([])", Object::Null),
+            ("last([1, 2, 3])", Object::Integer(3)),
+            ("rest([1, 2, 3])", Object::Array(vec![Object::Integer(2), Object::Integer(3)])),
+            ("rest([])", Object::Null),
+            ("let a = [1]; push(a, 2); a", Object::Array(vec![Object::Integer(1)])),
+            ("push([1], 2)", Object::Array(vec![Object::Integer(1), Object::Integer(2)])),
+            ("{\"foo\": 5}[\"foo\"]", Object::Integer(5)),
+            ("{\"foo\": 5}[\"bar\"]", Object::Null),
+            ("let key = \"foo\"; {\"foo\": 5}[key]", Object::Integer(5)),
+            ("{}[\"foo\"]", Object::Null),
+            ("{5: 5}[5]", Object::Integer(5)),
+            ("{true: 5}[true]", Object::Integer(5)),
+            ("{false: 5}[false]", Object::Integer(5)),
+            ("{1: 1, 1: 2}[1]", Object::Integer(2)),
+            ("let f = len; f([1, 2])", Object::Integer(2)),
+            ("let apply = fn(f, x) { f(x) }; apply(first, [7, 8])", Object::Integer(7)),
+            ("let len = fn(x) { 42 }; len([1])", Object::Integer(42)),
+            ("let f = fn() { let push = 3; push }; f() + len(push([1], 2))", Object::Integer(5)),
         ];
 
         for (input, answer) in probrem.iter() {
@@ -249,9 +636,9 @@ mod tests {
         let obj = eval(input);
 
         if let Object::Function{parameters, body, ..} = obj {
-            if let ast::Expression::Ident(ident) = &parameters[0] {
+            if let ast::ExpressionKind::Ident(ident) = &parameters[0].kind {
                 assert_eq!( ident, "x" );
-                if let ast::Statement::Block(blocks) = body {
+                if let ast::StatementKind::Block(blocks) = body.kind {
                     assert_eq!(format!("{}", blocks[0]), "(x+2);");
                 }
             } else {
@@ -270,4 +657,584 @@ mod tests {
         let input = "let a = 2; let f = fn(x) { x + a; }; f(0)";
         assert_eq!( eval(input), Object::Integer(2));
     }
+
+    #[test]
+    fn eval_closure_environment() {
+        // 後から定義された関数も定義元の環境を通して見える
+        let input = "
+            let is_even = fn(n) { if (n == 0) { true } else { is_odd(n - 1) } };
+            let is_odd = fn(n) { if (n == 0) { false } else { is_even(n - 1) } };
+            [is_even(10), is_odd(7), is_even(3)]
+        ";
+        assert_eq!( eval(input), Object::Array(vec![Object::Bool(true), Object::Bool(true), Object::Bool(false)]) );
+
+        // 定義後に束縛し直した値が見える
+        let input = "let a = 1; let f = fn() { a }; let a = 2; f()";
+        assert_eq!( eval(input), Object::Integer(2) );
+
+        // 呼び出しごとに新しい環境が作られ, 呼び出し同士で束縛が混ざらない
+        let input = "
+            let make = fn(x) { fn() { x } };
+            let one = make(1);
+            let two = make(2);
+            [one(), two(), one()]
+        ";
+        assert_eq!( eval(input), Object::Array(vec![Object::Integer(1), Object::Integer(2), Object::Integer(1)]) );
+
+        // 自分自身を参照する関数を表示しても循環しない
+        let f = eval("let f = fn() { f }; f");
+        assert!( format!("{:?}", f).contains("Environment") );
+    }
+
+    #[test]
+    fn eval_error_span() {
+        let input = "let a = 1;\nlet f = fn(x) { x };\nf(a, b);";
+        let mut env = Evaluator::new();
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().expect("Failed to parse!");
+
+        match env.eval(&program) {
+            Err(MonkeyError::IdentifierNotFound(ident, span)) => {
+                assert_eq!( ident, "b" );
+                assert_eq!( (span.start.line, span.start.column), (3, 6) );
+            },
+            result => panic!("expected IdentifierNotFound, but got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn eval_arithmetic_errors() {
+        use crate::operator::Infix;
+
+        let tests = [
+            ("5 / 0", MonkeyError::DivisionByZero(Box::new(Object::Integer(5)), Infix::Slash, Default::default())),
+            ("99999999999999999999 / (1 - 1)", MonkeyError::DivisionByZero(
+                Box::new(Object::integer(big("99999999999999999999"))), Infix::Slash, Default::default()
+            )),
+            (r#""ab" * 99999999999999999999"#, MonkeyError::TooLarge{ what: "string", span: Default::default() }),
+            ("5 % 0", MonkeyError::DivisionByZero(Box::new(Object::Integer(5)), Infix::Percent, Default::default())),
+            ("1 << -1", MonkeyError::NegativeShift(Box::new(Object::Integer(-1)), Default::default())),
+            ("1 >> -99999999999999999999", MonkeyError::NegativeShift(
+                Box::new(Object::integer(-big("99999999999999999999"))), Default::default()
+            )),
+            ("2 ** 100000", MonkeyError::TooLarge{ what: "integer", span: Default::default() }),
+            ("1 << 99999999999999999999", MonkeyError::TooLarge{ what: "integer", span: Default::default() }),
+            ("let x = 2 ** 40000; x * x", MonkeyError::TooLarge{ what: "integer", span: Default::default() }),
+            ("let x = 3; while (true) { x = x * x; }", MonkeyError::TooLarge{ what: "integer", span: Default::default() }),
+            ("1.5 & 1", MonkeyError::UnknownOperator(ObjectType::Float, Infix::BitAnd, ObjectType::Float, Default::default())),
+        ];
+        for (input, expected) in tests.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            match Evaluator::new().eval(&program) {
+                Err(e) => {
+                    assert_eq!( format!("{}", e), format!("{}", expected) );
+                    assert!( !e.span().is_dummy(), "{:?} has no span", e );
+                },
+                Ok(obj) => panic!("{} should fail, but got {}", input, obj),
+            }
+        }
+    }
+
+    #[test]
+    fn eval_full_operators() {
+        let tests = [
+            ("7 % 3", "1"),
+            ("-7 % 3", "-1"),
+            ("7 % -3", "1"),
+            ("(-9223372036854775807 - 1) % -1", "0"),
+            ("99999999999999999999 % 7", "1"),
+            ("7.5 % 2", "1.5"),
+            ("[1 <= 1, 1 <= 0, 2 >= 1, 1 >= 2, 1.5 <= 2, \"a\" <= \"a\", \"b\" >= \"c\"]",
+                "[true, false, true, false, true, true, false]"),
+            ("[99999999999999999999 >= 99999999999999999999, 1 <= 99999999999999999999]", "[true, true]"),
+            ("2 ** 10", "1024"),
+            ("2 ** 3 ** 2", "512"),
+            ("(2 ** 3) ** 2", "64"),
+            ("-2 ** 2", "-4"),
+            ("(-2) ** 3", "-8"),
+            ("2 ** 64", "18446744073709551616"),
+            ("10 ** 20 / 10 ** 19", "10"),
+            ("2 ** -1", "0.5"),
+            ("4 ** 0.5", "2.0"),
+            ("0 ** 0", "1"),
+            ("[(-1) ** 99999999999999999999, (-1) ** 99999999999999999998, 1 ** 99999999999999999999]", "[-1, 1, 1]"),
+            ("[6 & 3, 6 | 3, 6 ^ 3, -1 & 255, -8 ^ 3]", "[2, 7, 5, 255, -5]"),
+            ("[1 << 10, 1 << 63, 1 << 64, -1 << 63, 1024 >> 3, -1 >> 100, -7 >> 1, 5 >> 64]",
+                "[1024, 9223372036854775808, 18446744073709551616, -9223372036854775808, 128, -1, -4, 0]"),
+            ("(1 << 100) >> 99", "2"),
+            ("((1 << 100) | 1) & 3", "1"),
+            ("(1 << 64) ^ (1 << 64)", "0"),
+            ("1 + 2 << 3 & 255 == 24", "true"),
+        ];
+        for (input, expected) in tests.iter() {
+            assert_eq!( &format!("{}", eval(input)), expected, "input: {}", input );
+        }
+    }
+
+    #[test]
+    fn eval_while() {
+        let tests = [
+            ("let i = 0; while (i < 100000) { i += 1; } i", "100000"),
+            ("while (false) { 1 }", "null"),
+            ("let i = 0; let sum = 0; while (true) { i += 1; if (i > 10) { break; } if (i % 2 == 0) { continue; } sum += i; } sum",
+                "25"),
+            ("let i = 0; let n = 0; while (i < 3) { i += 1; let j = 0; while (true) { j += 1; if (j == 4) { break; } n += 1; } } n",
+                "9"),
+            ("let find = fn(xs, x) { let i = 0; while (i < len(xs)) { if (xs[i] == x) { return i; } i += 1; } -1 }; [find([5, 6, 7], 7), find([], 1)]",
+                "[2, -1]"),
+            // 式の途中の break, continue, return も値にならずにループや関数まで巻き戻される
+            ("let i = 0; let n = 0; while (i < 3) { i += 1; let x = if (true) { break; }; n += 1; } [i, n]", "[1, 0]"),
+            ("let s = 0; let i = 0; while (i < 3) { i += 1; s += 1 + if (i == 2) { continue; } else { i }; } s", "6"),
+            ("let n = 0; while (true) { while (if (true) { break; }) { } n = 1; break; } n", "0"),
+            ("let f = fn() { let x = if (true) { return 5; }; 10 }; f()", "5"),
+            ("let x = if (true) { return 7; }; 10", "7"),
+        ];
+        for (input, expected) in tests.iter() {
+            assert_eq!( &format!("{}", eval(input)), expected, "input: {}", input );
+        }
+    }
+
+    #[test]
+    fn eval_block_scope() {
+        let tests = [
+            // ブロックの中の束縛は外側の同じ名前を隠し, ブロックを出ると元に戻る
+            ("let x = 1; if (true) { let x = 2; x }", "2"),
+            ("let x = 1; if (true) { let x = 2; } x", "1"),
+            ("let x = 1; if (false) { 0 } else { let x = 3; } x", "1"),
+            ("let x = 1; if (true) { let y = x + 1; if (true) { let x = y * 10; x } }", "20"),
+            ("let f = fn(x) { if (x > 0) { let x = x * 2; } x }; f(5)", "5"),
+            ("let x = 1; while (x < 3) { let y = x; x += 1; } x", "3"),
+            ("let x = 1; { let x = 2; } x", "1"),
+            ("let x = 1; { let x = 2; { let x = x * 10; x } }", "20"),
+            ("let x = 1; { x = 2; }; x", "2"),
+            // 代入は外側の束縛を書き換える
+            ("let x = 1; if (true) { x = 2; } x", "2"),
+            ("let x = 1; if (true) { let x = 2; x = 3; } x", "1"),
+            // 繰り返しごとに新しい環境になる
+            ("let fs = []; let i = 0; while (i < 3) { let j = i; fs = push(fs, fn() { j }); i += 1; } [fs[0](), fs[2]()]", "[0, 2]"),
+            ("let fs = []; for (i in range(3)) { fs = push(fs, fn() { i }); } [fs[0](), fs[2]()]", "[0, 2]"),
+            // 関数本体は引数と同じ環境
+            ("let f = fn(x) { let x = x + 1; x }; f(1)", "2"),
+        ];
+        for (input, expected) in tests.iter() {
+            assert_eq!( &format!("{}", eval(input)), expected, "input: {}", input );
+        }
+
+        // ブロックの中の束縛は外に漏れない
+        let leaks = [
+            ("if (true) { let y = 1; } y", "y"),
+            ("{ let z = 1; } z", "z"),
+            ("while (true) { let z = 1; break; } z", "z"),
+            ("for (x in [1]) { x } x", "x"),
+            ("let f = fn() { if (true) { let w = 1; } w }; f()", "w"),
+        ];
+        for (input, name) in leaks.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            match Evaluator::new().eval(&program) {
+                Err(MonkeyError::IdentifierNotFound(got, _)) => assert_eq!( &got, name, "input: {}", input ),
+                result => panic!("expected IdentifierNotFound for {}, but got {:?}", input, result),
+            }
+        }
+
+        // エラーで抜けても環境は元に戻る
+        let mut env = Evaluator::new();
+        let program = Parser::new(Lexer::new("let x = 1; if (true) { let x = 2; x + true }")).parse_program().unwrap();
+        assert!( env.eval(&program).is_err() );
+        assert_eq!( env.get("x"), Some(Object::Integer(1)) );
+    }
+
+    #[test]
+    fn eval_assign() {
+        let tests = [
+            ("let x = 1; x = 2; x", "2"),
+            ("let x = 10; x += 5; x -= 3; x *= 2; x /= 4; x", "6"),
+            ("let s = \"a\"; s += \"b\"; s *= 2; s", "abab"),
+            ("let x = 1; let f = fn() { x = 2; }; f(); x", "2"),
+            ("let x = 1; let f = fn(x) { x = 5; x }; [f(0), x]", "[5, 1]"),
+            ("let counter = fn() { let n = 0; fn() { n += 1; n } }; let c = counter(); c(); c(); c()", "3"),
+            ("let i = 0; while (i < 5) { i += 1; } i", "5"),
+            ("let x = 1; x = 2", "null"),
+        ];
+        for (input, expected) in tests.iter() {
+            assert_eq!( &format!("{}", eval(input)), expected, "input: {}", input );
+        }
+
+        let tests = [
+            ("y = 1;", "y", 1),
+            ("let f = fn() { z = 1; }; f()", "z", 16),
+            ("let f = fn() { let g = fn() { w = 1; }; g() }; f()", "w", 31),
+            // 複合代入も代入先を読む前に確かめる
+            ("x += 1;", "x", 1),
+            ("let f = fn() { z *= 2; }; f()", "z", 16),
+        ];
+        for (input, name, column) in tests.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            match Evaluator::new().eval(&program) {
+                Err(MonkeyError::AssignToUndeclared(got, span)) => {
+                    assert_eq!( (got.as_str(), span.start.column), (*name, *column), "input: {}", input );
+                },
+                result => panic!("expected AssignToUndeclared for {}, but got {:?}", input, result),
+            }
+        }
+    }
+
+    #[test]
+    fn eval_const() {
+        let tests = [
+            ("const x = 1; x + 1", "2"),
+            ("const x = 1; if (true) { const x = 2; x }", "2"),
+            ("const x = 1; if (true) { let x = 2; x = 3; } x", "1"),
+            ("let x = 1; const x = 2; x", "2"),
+            ("let s = 0; for (i in range(3)) { const j = i * 2; s += j; } s", "6"),
+            ("const f = fn(x) { x = x + 1; x }; f(1)", "2"),
+        ];
+        for (input, expected) in tests.iter() {
+            assert_eq!( &format!("{}", eval(input)), expected, "input: {}", input );
+        }
+
+        let tests = [
+            ("const x = 1; x = 2;", "x", 14),
+            ("const x = 1; x += 2;", "x", 14),
+            ("const x = 1; let x = 2;", "x", 18),
+            ("const x = 1; const x = 2;", "x", 20),
+            ("const x = 1; let f = fn() { x = 2; }; f()", "x", 29),
+            ("const x = 1; if (true) { x = 2; }", "x", 26),
+        ];
+        for (input, name, column) in tests.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            match Evaluator::new().eval(&program) {
+                Err(MonkeyError::AssignToConstant(got, span)) => {
+                    assert_eq!( (got.as_str(), span.start.column), (*name, *column), "input: {}", input );
+                },
+                result => panic!("expected AssignToConstant for {}, but got {:?}", input, result),
+            }
+        }
+    }
+
+    #[test]
+    fn eval_for() {
+        let tests = [
+            ("let sum = 0; for (x in [1, 2, 3]) { sum += x; } sum", "6"),
+            ("let s = \"\"; for (c in \"añb\") { s = c + s; } s", "bña"),
+            ("let ks = []; for (k in {\"b\": 1, \"a\": 2, 3: 3}) { ks = push(ks, k); } ks", "[b, a, 3]"),
+            ("for (x in []) { 1 }", "null"),
+            ("let x = 1; for (x in [7, 8]) { x } x", "1"),
+            ("let sum = 0; for (i in range(10)) { if (i == 7) { break; } if (i % 2 == 0) { continue; } sum += i; } sum",
+                "9"),
+            ("let f = fn(xs) { for (x in xs) { if (x > 1) { return x; } } 0 }; [f([1, 5, 9]), f([])]", "[5, 0]"),
+            ("let n = 0; for (i in range(3)) { for (j in range(i)) { n += 1; } } n", "3"),
+        ];
+        for (input, expected) in tests.iter() {
+            assert_eq!( &format!("{}", eval(input)), expected, "input: {}", input );
+        }
+
+        let program = Parser::new(Lexer::new("for (x in 5) { x }")).parse_program().unwrap();
+        match Evaluator::new().eval(&program) {
+            Err(MonkeyError::NotIterable(ObjectType::Integer, span)) => assert_eq!( span.start.column, 11 ),
+            result => panic!("expected NotIterable, but got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn eval_range() {
+        let tests = [
+            ("range(4)", "[0, 1, 2, 3]"),
+            ("range(2, 5)", "[2, 3, 4]"),
+            ("range(0, 10, 3)", "[0, 3, 6, 9]"),
+            ("range(5, 0, -2)", "[5, 3, 1]"),
+            ("range(5, 5)", "[]"),
+            ("range(5, 0)", "[]"),
+            ("range(0, 5, -1)", "[]"),
+            ("range(-9223372036854775808, 9223372036854775807, 9223372036854775807)",
+                "[-9223372036854775808, -1, 9223372036854775806]"),
+        ];
+        for (input, expected) in tests.iter() {
+            assert_eq!( &format!("{}", eval(input)), expected, "input: {}", input );
+        }
+
+        let errors = ["range()", "range(1, 2, 3, 4)", "range(\"a\")", "range(0, 1.5)", "range(0, 5, 0)", "range(0, 1 << 40)"];
+        for input in errors.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            match Evaluator::new().eval(&program) {
+                Err(e) => assert!( !e.span().is_dummy(), "{:?} has no span", e ),
+                Ok(obj) => panic!("{} should fail, but got {}", input, obj),
+            }
+        }
+
+        // 引数の数のエラーは受け付ける範囲を示す
+        for (input, got) in [("range()", 0), ("range(1, 2, 3, 4)", 4)].iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            match Evaluator::new().eval(&program) {
+                Err(e @ MonkeyError::IncorrectNumberOfArgsRange{min: 1, max: 3, ..}) => {
+                    assert_eq!( format!("{}", e), format!("wrong number of arguments: expected 1 to 3, got {}", got) );
+                    assert_eq!( e.hint().unwrap(), "this function takes 1 to 3 arguments" );
+                },
+                result => panic!("expected IncorrectNumberOfArgsRange for {}, but got {:?}", input, result),
+            }
+        }
+    }
+
+    #[test]
+    fn eval_logical_operators() {
+        let tests = [
+            ("true && false", "false"),
+            ("true && 5", "5"),
+            ("1 && 2", "2"),
+            ("false || \"default\"", "default"),
+            ("0 || 1", "0"),
+            ("if (false) { 1 } || 2", "2"),
+            ("1 < 2 && 2 < 3", "true"),
+            ("false && true || true", "true"),
+            // 右辺は必要なときだけ評価される
+            ("false && undefined", "false"),
+            ("true || undefined()", "true"),
+            ("let calls = fn() { 1 / 0 }; [true || calls(), false && calls()]", "[true, false]"),
+        ];
+        for (input, expected) in tests.iter() {
+            assert_eq!( &format!("{}", eval(input)), expected, "input: {}", input );
+        }
+
+        let program = Parser::new(Lexer::new("true && undefined")).parse_program().unwrap();
+        assert!( Evaluator::new().eval(&program).is_err() );
+    }
+
+    #[test]
+    fn eval_string_operators() {
+        let tests = [
+            (r#""Hello" + ", " + "World!""#, Object::String("Hello, World!".to_owned())),
+            (r#"let s = "ab"; s + s"#, Object::String("abab".to_owned())),
+            (r#""" + """#, Object::String("".to_owned())),
+            (r#""ab" * 3"#, Object::String("ababab".to_owned())),
+            (r#""ab" * 0"#, Object::String("".to_owned())),
+            (r#""ab" * -2"#, Object::String("".to_owned())),
+            (r#""abc" == "abc""#, Object::Bool(true)),
+            (r#""abc" == "abd""#, Object::Bool(false)),
+            (r#""abc" != "abd""#, Object::Bool(true)),
+            (r#""abc" < "abd""#, Object::Bool(true)),
+            (r#""b" > "abc""#, Object::Bool(true)),
+            (r#""ab" < "ab""#, Object::Bool(false)),
+            (r#""" < "a""#, Object::Bool(true)),
+            (r#"len("ab" * 50)"#, Object::Integer(100)),
+        ];
+        for (input, expected) in tests.iter() {
+            assert_eq!( &eval(input), expected, "input: {}", input );
+        }
+
+        let errors = [
+            (r#""a" + 1"#, "type mismatch: string + integer"),
+            (r#"1 + "a""#, "type mismatch: integer + string"),
+            (r#"3 * "ab""#, "type mismatch: integer * string"),
+            (r#""1" == 1"#, "type mismatch: string == integer"),
+            (r#""ab" * true"#, "type mismatch: string * boolean"),
+            (r#""ab" / "a""#, "unknown operator: string / string"),
+            (r#""ab" * 2147483647"#, "the resulting string is too large"),
+        ];
+        for (input, expected) in errors.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            match Evaluator::new().eval(&program) {
+                Err(e) => assert_eq!( &format!("{}", e), expected ),
+                Ok(obj) => panic!("{} should fail, but got {}", input, obj),
+            }
+        }
+    }
+
+    #[test]
+    fn eval_float() {
+        let tests = [
+            ("3.14", "3.14"),
+            ("1e-9", "1e-9"),
+            ("2.5E3", "2500.0"),
+            ("-1.5", "-1.5"),
+            ("0.1 + 0.2", "0.30000000000000004"),
+            ("1 + 0.5", "1.5"),
+            ("0.5 * 4", "2.0"),
+            ("3 - 1.0", "2.0"),
+            ("7 / 2", "3"),
+            ("-7 / 2", "-3"),
+            ("7 / 2.0", "3.5"),
+            ("7.0 / 2", "3.5"),
+            ("1 / 0.0", "inf"),
+            ("-1 / 0.0", "-inf"),
+            ("0.0 / 0.0", "NaN"),
+            ("let nan = 0.0 / 0.0; [nan == nan, nan != nan, nan < 1, nan > 1]", "[false, true, false, false]"),
+            ("let inf = 1.0 / 0; [inf > 1e308, -inf < -1e308, inf == inf, inf - inf]", "[true, true, true, NaN]"),
+            ("[1 == 1.0, 1 < 1.5, 2 > 1.5, 0.0 == -0.0]", "[true, true, true, true]"),
+            ("[3.0, 1e20, 1.5e-7]", "[3.0, 1e20, 1.5e-7]"),
+            ("2147483647 + 1.0", "2147483648.0"),
+        ];
+        for (input, expected) in tests.iter() {
+            assert_eq!( &format!("{}", eval(input)), expected, "input: {}", input );
+        }
+        assert_eq!( eval("1.5").type_of(), ObjectType::Float );
+        assert_eq!( eval("2 * 1.0").type_of(), ObjectType::Float );
+        assert_eq!( eval("4 / 2").type_of(), ObjectType::Integer );
+
+        let errors = [
+            ("1.5 + true", "type mismatch: float + boolean"),
+            ("\"a\" * 1.5", "type mismatch: string * float"),
+            ("{1.5: 1}", "unusable as hash key: float"),
+            ("[1][0.0]", "cannot index array with float"),
+        ];
+        for (input, expected) in errors.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            match Evaluator::new().eval(&program) {
+                Err(e) => assert_eq!( &format!("{}", e), expected ),
+                Ok(obj) => panic!("{} should fail, but got {}", input, obj),
+            }
+        }
+    }
+
+    #[test]
+    fn eval_big_integer() {
+        let tests = [
+            ("2147483647 + 1", "2147483648"),
+            ("65536 * 65536", "4294967296"),
+            ("9223372036854775807 + 1", "9223372036854775808"),
+            ("-9223372036854775807 - 2", "-9223372036854775809"),
+            ("-(-9223372036854775807 - 1)", "9223372036854775808"),
+            ("(-9223372036854775807 - 1) / -1", "9223372036854775808"),
+            ("4294967296 * 4294967296 * 4294967296", "79228162514264337593543950336"),
+            ("99999999999999999999 / 3", "33333333333333333333"),
+            ("-99999999999999999999 / 7", "-14285714285714285714"),
+            ("99999999999999999999 - 99999999999999999998", "1"),
+            ("[99999999999999999999 > 9223372036854775807, -99999999999999999999 < 1, 18446744073709551616 == 2 * 9223372036854775808]",
+                "[true, true, true]"),
+            ("99999999999999999999 * 1.0", "1e20"),
+            ("{99999999999999999999: 1}[99999999999999999998 + 1]", "1"),
+        ];
+        for (input, expected) in tests.iter() {
+            assert_eq!( &format!("{}", eval(input)), expected, "input: {}", input );
+        }
+        // i64 に収まる結果は Object::Integer に戻る
+        assert_eq!( eval("9223372036854775808 - 1"), Object::Integer(i64::MAX) );
+        assert_eq!( eval("99999999999999999999 / 99999999999999999999"), Object::Integer(1) );
+        assert_eq!( eval("9223372036854775808"), Object::BigInteger(big("9223372036854775808")) );
+        assert_eq!( eval("9223372036854775808").type_of(), ObjectType::Integer );
+    }
+
+    #[test]
+    fn eval_operator_errors() {
+        let tests = [
+            ("-true", "unknown operator: -boolean"),
+            ("-\"abc\"", "unknown operator: -string"),
+            ("-[1]", "unknown operator: -array"),
+            ("-fn() { 1 }", "unknown operator: -function"),
+            ("-len", "unknown operator: -builtin function"),
+            ("true + false", "unknown operator: boolean + boolean"),
+            ("true < false", "unknown operator: boolean < boolean"),
+            ("\"a\" - \"b\"", "unknown operator: string - string"),
+            ("[1] + [2]", "unknown operator: array + array"),
+            ("{} == {}", "unknown operator: hash == hash"),
+            ("fn() { 1 } == fn() { 1 }", "unknown operator: function == function"),
+            ("if (false) { 1 } == if (false) { 2 }", "unknown operator: null == null"),
+            ("1 + true", "type mismatch: integer + boolean"),
+            ("1 == \"1\"", "type mismatch: integer == string"),
+            ("\"a\" + 1", "type mismatch: string + integer"),
+            ("[1] == if (false) { 1 }", "type mismatch: array == null"),
+            ("len == fn(x) { x }", "type mismatch: builtin function == function"),
+        ];
+        for (input, expected) in tests.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            match Evaluator::new().eval(&program) {
+                Err(e @ MonkeyError::UnknownOperator(..))
+                    | Err(e @ MonkeyError::UnknownPrefixOperator(..))
+                    | Err(e @ MonkeyError::TypeMismatch(..)) => {
+                    assert_eq!( &format!("{}", e), expected );
+                    assert!( !e.span().is_dummy(), "{:?} has no span", e );
+                },
+                result => panic!("{} should fail with {}, but got {:?}", input, expected, result),
+            }
+        }
+    }
+
+    #[test]
+    fn eval_type_of() {
+        let tests = [
+            ("1", ObjectType::Integer), ("true", ObjectType::Bool), ("if (false) { 1 }", ObjectType::Null),
+            ("\"a\"", ObjectType::String), ("[]", ObjectType::Array), ("{}", ObjectType::Hash),
+            ("fn() { 1 }", ObjectType::Function), ("len", ObjectType::Builtin),
+        ];
+        for (input, expected) in tests.iter() {
+            assert_eq!( &eval(input).type_of(), expected, "input: {}", input );
+        }
+    }
+
+    #[test]
+    fn eval_not_callable() {
+        let tests = [
+            ("let x = 5; x(1);", ObjectType::Integer, (1, 12)),
+            ("\"f\"()", ObjectType::String, (1, 1)),
+            ("[fn() { 1 }][0]()()", ObjectType::Integer, (1, 1)),
+            ("let f = fn() { true }; f()()", ObjectType::Bool, (1, 24)),
+        ];
+        for (input, callee, (line, column)) in tests.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            match Evaluator::new().eval(&program) {
+                Err(MonkeyError::NotCallable(t, span)) => {
+                    assert_eq!( &t, callee );
+                    assert_eq!( (span.start.line, span.start.column), (*line, *column) );
+                },
+                result => panic!("expected NotCallable for {}, but got {:?}", input, result),
+            }
+        }
+    }
+
+    #[test]
+    fn eval_array_errors() {
+        let problem = [
+            "[1, 2, 3][3]",
+            "[1, 2, 3][-1]",
+            "[1][true]",
+            "1[0]",
+            "len(1)",
+            "len([1], [2])",
+            "push(1, 1)",
+            "{fn(x) { x }: 1}",
+            "{[1]: 1}",
+            "{1: 2}[[1]]",
+        ];
+
+        for input in problem.iter() {
+            let mut env = Evaluator::new();
+            let program = Parser::new(Lexer::new(input)).parse_program().expect("Failed to parse!");
+            match env.eval(&program) {
+                Err(e) => assert!( !e.span().is_dummy(), "{:?} has no span", e ),
+                Ok(obj) => panic!("{} should fail, but got {}", input, obj),
+            }
+        }
+
+        let program = Parser::new(Lexer::new("[1, 2, 3][3]")).parse_program().unwrap();
+        match Evaluator::new().eval(&program) {
+            Err(MonkeyError::IndexOutOfBounds{index, length, ..}) => assert_eq!( (*index, length), (Object::Integer(3), 3) ),
+            result => panic!("expected IndexOutOfBounds, but got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn eval_hash_literal() {
+        let input = r#"
+let two = "two";
+{
+    "one": 10 - 9,
+    two: 1 + 1,
+    "thr" + "ee": 6 / 2,
+    4: 4,
+    true: 5,
+    false: 6
+}"#;
+        match eval(input) {
+            Object::Hash(table) => {
+                let keys: Vec<_> = table.iter().map(|(key, _)| format!("{}", key)).collect();
+                assert_eq!( keys, vec!["one", "two", "three", "4", "true", "false"] );
+            },
+            obj => panic!("expected hash, but got {}", obj),
+        }
+
+        let program = Parser::new(Lexer::new("{\"a\": 1}[fn(x) { x }]")).parse_program().unwrap();
+        match Evaluator::new().eval(&program) {
+            Err(MonkeyError::UnhashableKey(ObjectType::Function, _)) => {},
+            result => panic!("expected UnhashableKey, but got {:?}", result),
+        }
+    }
 }
@@ -1,4 +1,9 @@
-use crate::token::Token;
+use crate::{
+    token::{Token, SpannedToken},
+    span::{Position, Span},
+    error::LexError,
+    bigint::BigInt,
+};
 
 /// 字句解析器
 /// 入力ソースコードのライフタイムをライフタイムとする.
@@ -7,6 +12,10 @@ pub struct Lexer<'a> {
     input: std::str::Chars<'a>,
     cur: char,
     peek: char,
+    /// cur の位置
+    pos: Position,
+    /// true のときコメントを Token::Comment として出力する.
+    trivia: bool,
 }
 
 impl<'a> Lexer<'a> {
@@ -16,15 +25,34 @@ impl<'a> Lexer<'a> {
             input: input.chars(),
             cur:  '\u{0}',
             peek: '\u{0}',
+            pos:  Position::start(),
+            trivia: false,
         };
         lexer.read_char();
         lexer.read_char();
         lexer
     }
 
+    /// コメントを読み飛ばさず Token::Comment として出力するようにする.
+    /// コメントを保持する必要のあるフォーマッタなどのためのもので, crate::parser::Parser はコメントを無視する.
+    pub fn with_trivia(mut self) -> Self {
+        self.trivia = true;
+        self
+    }
+
     /// 1 文字進む.
     fn read_char(&mut self) -> char {
         let c = self.cur;
+        // 入力の終端 (および初期化時) には位置を進めない
+        if c != '\u{0}' {
+            self.pos.offset += c.len_utf8();
+            if c == '\n' {
+                self.pos.line += 1;
+                self.pos.column = 1;
+            } else {
+                self.pos.column += 1;
+            }
+        }
         self.cur = self.peek;
         self.peek = self.input.next().unwrap_or('\u{0}');
         c
@@ -51,30 +79,223 @@ impl<'a> Lexer<'a> {
         }
     }
     
-    /// 数字一つ分を読み込みトークンに変換する.
+    /// 数値一つ分を読み込みトークンに変換する.
+    /// 小数部 (`.` の直後に数字が続くもの) か指数部 (`e` または `E`) があれば浮動小数点数になる.
+    /// `0x`, `0o`, `0b` で始まれば 16 進, 8 進, 2 進の整数になる. 数字の間には区切り文字 `_` を置ける.
     fn read_number(&mut self) -> Token {
+        if self.cur == '0' && (self.peek == 'x' || self.peek == 'o' || self.peek == 'b') {
+            return self.read_radix_integer();
+        }
+
         let mut number = String::new();
-        while self.cur.is_ascii_digit() {
+        let mut well_formed = self.read_digits(&mut number, 10);
+
+        let mut is_float = false;
+        if self.cur == '.' && self.peek.is_ascii_digit() {
+            is_float = true;
+            number.push(self.read_char());
+            well_formed &= self.read_digits(&mut number, 10);
+        }
+        if self.cur == 'e' || self.cur == 'E' {
+            is_float = true;
+            number.push(self.read_char());
+            if self.cur == '+' || self.cur == '-' {
+                number.push(self.read_char());
+            }
+            if !self.cur.is_ascii_digit() {
+                return Token::Error(LexError::MalformedFloat(number));
+            }
+            well_formed &= self.read_digits(&mut number, 10);
+        }
+        if !well_formed {
+            return Token::Error(LexError::MisplacedSeparator(number));
+        }
+
+        let digits = number.replace('_', "");
+        if is_float {
+            match digits.parse::<f64>() {
+                Ok(value) if value.is_finite() => Token::Float(value),
+                _ => Token::Error(LexError::FloatOverflow(number)),
+            }
+        } else {
+            integer_token(&digits, 10)
+        }
+    }
+
+    /// `0x`, `0o`, `0b` で始まる整数を読み込む.
+    fn read_radix_integer(&mut self) -> Token {
+        let radix = match self.peek {
+            'x' => 16,
+            'o' => 8,
+            _   => 2,
+        };
+        let mut literal = String::new();
+        literal.push(self.read_char());
+        literal.push(self.read_char());
+        let well_formed = self.read_digits(&mut literal, radix);
+
+        // 基数に合わない数字や英字が続けば, それらも含めて一つの不正なリテラルとする
+        if self.cur.is_ascii_alphanumeric() {
+            let digit = self.cur;
+            while self.cur.is_ascii_alphanumeric() || self.cur == '_' {
+                literal.push(self.read_char());
+            }
+            return Token::Error(LexError::InvalidDigit{ literal, digit, radix });
+        }
+        let digits = literal[2..].replace('_', "");
+        if digits.is_empty() {
+            Token::Error(LexError::MissingDigits(literal))
+        } else if !well_formed {
+            Token::Error(LexError::MisplacedSeparator(literal))
+        } else {
+            integer_token(&digits, radix)
+        }
+    }
+
+    /// radix 進数の数字と区切り文字 `_` を読み込み number に追加する.
+    /// `_` が数字に挟まれていないものがあれば false を返す.
+    fn read_digits(&mut self, number: &mut String, radix: u32) -> bool {
+        let mut well_formed = true;
+        while self.cur.is_digit(radix) || self.cur == '_' {
+            if self.cur == '_' {
+                let after_digit = number.chars().last().is_some_and(|c| c.is_digit(radix));
+                well_formed &= after_digit && self.peek.is_digit(radix);
+            }
             number.push(self.read_char());
         }
-        Token::Integer(number.parse().unwrap())
+        well_formed
     }
 
-    /// 文字列を読み込みトークンに変換する.
+    /// 文字列を読み込みトークンに変換する. 終了時には閉じる `"` の位置にいる.
+    /// 不正なエスケープシーケンスがあっても閉じる `"` まで読み進め, 最初のエラーを返す.
     fn read_string(&mut self) -> Token {
         self.read_char();
 
         let mut s = String::new();
-        while self.cur != '"' && self.cur != '\u{0}' {
-            s.push(self.read_char());
+        let mut error = None;
+        loop {
+            match self.cur {
+                '"'      => break,
+                '\u{0}'  => return Token::Error(LexError::UnterminatedString),
+                '\\'     => match self.read_escape() {
+                    Ok(c)  => s.push(c),
+                    Err(e) => { error.get_or_insert(e); },
+                },
+                _        => s.push(self.read_char()),
+            }
+        }
+        match error {
+            Some(e) => Token::Error(e),
+            None    => Token::String(s),
+        }
+    }
+
+    /// `\` から始まるエスケープシーケンスを読み込み, それが表す文字を返す.
+    fn read_escape(&mut self) -> Result<char, LexError> {
+        self.read_char();
+        match self.read_char() {
+            '"'  => Ok('"'),
+            '\\' => Ok('\\'),
+            'n'  => Ok('\n'),
+            't'  => Ok('\t'),
+            'r'  => Ok('\r'),
+            '0'  => Ok('\u{0}'),
+            'u'  => self.read_unicode_escape(),
+            c    => Err(LexError::InvalidEscape(format!("\\{}", c))),
+        }
+    }
+
+    /// `\u` に続く `{...}` を読み込む. 括弧の中は 1 桁以上 6 桁以下の 16 進数で, Unicode スカラー値でなければならない.
+    fn read_unicode_escape(&mut self) -> Result<char, LexError> {
+        let mut seq = String::from("\\u");
+        if self.cur != '{' {
+            return Err(LexError::InvalidEscape(seq));
+        }
+        seq.push(self.read_char());
+
+        let mut digits = String::new();
+        while self.cur.is_ascii_hexdigit() {
+            digits.push(self.read_char());
+        }
+        seq.push_str(&digits);
+        if self.cur != '}' {
+            return Err(LexError::InvalidEscape(seq));
+        }
+        seq.push(self.read_char());
+
+        Some(digits)
+            .filter(|digits| !digits.is_empty() && digits.len() <= 6)
+            .and_then(|digits| u32::from_str_radix(&digits, 16).ok())
+            .and_then(char::from_u32)
+            .ok_or(LexError::InvalidEscape(seq))
+    }
+
+    /// 次の文字が second なら二文字の演算子 double を, そうでなければ一文字の演算子 single を返す.
+    fn read_double(&mut self, second: char, double: Token, single: Token) -> Token {
+        if self.peek == second {
+            self.read_char();
+            double
+        } else {
+            single
         }
-        Token::String(s.to_owned())
     }
 
     /// 次のトークンを生成する.
-    pub fn next_token(&mut self) -> Token {
-        self.skip_whitespace();
+    pub fn next_token(&mut self) -> SpannedToken {
+        loop {
+            self.skip_whitespace();
+
+            let start = self.pos;
+            let token = match (self.cur, self.peek) {
+                ('/', '/') => self.read_line_comment(),
+                ('/', '*') => self.read_block_comment(),
+                _          => self.read_token(),
+            };
+            if let Token::Comment(_) = token {
+                if !self.trivia {
+                    continue;
+                }
+            }
+            return SpannedToken { token, span: Span::new(start, self.pos) };
+        }
+    }
 
+    /// `//` から行末までのコメントを読み込む. 改行文字は含まない.
+    fn read_line_comment(&mut self) -> Token {
+        let mut comment = String::new();
+        while self.cur != '\n' && self.cur != '\u{0}' {
+            comment.push(self.read_char());
+        }
+        if comment.ends_with('\r') {
+            comment.pop();
+        }
+        Token::Comment(comment)
+    }
+
+    /// `/*` から対応する `*/` までのコメントを読み込む. ブロックコメントは入れ子にできる.
+    fn read_block_comment(&mut self) -> Token {
+        let mut comment = String::new();
+        let mut depth = 0usize;
+        loop {
+            match (self.cur, self.peek) {
+                ('\u{0}', _) => return Token::Error(LexError::UnterminatedComment),
+                ('/', '*') => depth += 1,
+                ('*', '/') => depth -= 1,
+                _ => {
+                    comment.push(self.read_char());
+                    continue;
+                },
+            }
+            comment.push(self.read_char());
+            comment.push(self.read_char());
+            if depth == 0 {
+                return Token::Comment(comment);
+            }
+        }
+    }
+
+    /// 現在位置からトークン一つ分を読み込む.
+    fn read_token(&mut self) -> Token {
         let token = match self.cur {
             '=' => {
                 if self.peek == '=' {
@@ -93,17 +314,36 @@ impl<'a> Lexer<'a> {
                 }
             },
             ';' => Token::Semicolon,
+            ':' => Token::Colon,
             ',' => Token::Comma, 
             '(' => Token::LParen,
             ')' => Token::RParen,
             '{' => Token::LBrace,
             '}' => Token::RBrace,
-            '+' => Token::Plus, 
-            '-' => Token::Minus,
-            '*' => Token::Asterisk,
-            '/' => Token::Slash,
-            '<' => Token::LT, 
-            '>' => Token::GT, 
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '+' => self.read_double('=', Token::PlusAssign, Token::Plus),
+            '-' => self.read_double('=', Token::MinusAssign, Token::Minus),
+            '*' => match self.peek {
+                '*' => { self.read_char(); Token::Power },
+                '=' => { self.read_char(); Token::AsteriskAssign },
+                _   => Token::Asterisk,
+            },
+            '/' => self.read_double('=', Token::SlashAssign, Token::Slash),
+            '%' => Token::Percent,
+            '<' => match self.peek {
+                '=' => { self.read_char(); Token::LTEq },
+                '<' => { self.read_char(); Token::Shl },
+                _   => Token::LT,
+            },
+            '>' => match self.peek {
+                '=' => { self.read_char(); Token::GTEq },
+                '>' => { self.read_char(); Token::Shr },
+                _   => Token::GT,
+            },
+            '&' => self.read_double('&', Token::And, Token::Ampersand),
+            '|' => self.read_double('|', Token::Or, Token::Pipe),
+            '^' => Token::Caret,
             '"' => self.read_string(),
             '\u{0}' => Token::EOF,
             c => { 
@@ -121,14 +361,23 @@ impl<'a> Lexer<'a> {
     }
 }
 
+/// radix 進数の数字列を整数トークンにする. i64 に収まらなければ多倍長整数にする.
+fn integer_token(digits: &str, radix: u32) -> Token {
+    match i64::from_str_radix(digits, radix) {
+        Ok(value) => Token::Integer(value),
+        // digits は radix 進数の数字のみからなるので変換は失敗しない
+        Err(_)    => BigInt::from_str_radix(digits, radix).map_or(Token::Illegal, |n| Token::BigInteger(Box::new(n))),
+    }
+}
+
 fn is_letter(c: char) -> bool {
-    'a' <= c && c <= 'z' || 'A' <= c && c <= 'Z' || c == '_'
+    c.is_ascii_alphabetic() || c == '_'
 }
 
 
 #[cfg(test)]
 mod tests {
-    use crate::{token::Token, lexer::Lexer};
+    use crate::{token::Token, lexer::Lexer, span::Position, error::LexError, bigint::BigInt};
 
     #[test]
     fn read() {
@@ -147,6 +396,207 @@ mod tests {
         assert_eq!( lexer.read_number(), Token::Integer(12345));
     }
 
+    #[test]
+    fn read_string_escapes() {
+        let tests = [
+            (r#""a\"b""#, "a\"b"),
+            (r#""line\nnext\ttab\r""#, "line\nnext\ttab\r"),
+            (r#""back\\slash\\""#, "back\\slash\\"),
+            (r#""nul\0""#, "nul\u{0}"),
+            (r#""\u{41}\u{3042}\u{1F600}""#, "Aあ😀"),
+            (r#""\u{000041}""#, "A"),
+            (r#""""#, ""),
+        ];
+        for (input, expected) in tests.iter() {
+            let mut lexer = Lexer::new(input);
+            assert_eq!( lexer.next_token().token, Token::String(expected.to_string()), "input: {}", input );
+            assert_eq!( lexer.next_token().token, Token::EOF );
+        }
+    }
+
+    #[test]
+    fn read_invalid_escapes() {
+        let tests = [
+            (r#""\q""#, r"\q"),
+            (r#""\u41""#, r"\u"),
+            (r#""\u{}""#, r"\u{}"),
+            (r#""\u{110000}""#, r"\u{110000}"),
+            (r#""\u{D800}""#, r"\u{D800}"),
+            (r#""\u{0000041}""#, r"\u{0000041}"),
+            (r#""\u{41""#, r"\u{41"),
+            (r#""\u{4g}""#, r"\u{4"),
+            (r#""ok\x\y""#, r"\x"),
+        ];
+        for (input, expected) in tests.iter() {
+            let mut lexer = Lexer::new(input);
+            let token = lexer.next_token();
+            assert_eq!( token.token, Token::Error(LexError::InvalidEscape(expected.to_string())), "input: {}", input );
+            // 不正なエスケープの後も閉じる `"` の次から読み進める
+            assert_eq!( token.span.end.offset, input.len() );
+            assert_eq!( lexer.next_token().token, Token::EOF );
+        }
+    }
+
+    #[test]
+    fn read_unterminated_string() {
+        for input in [r#"let s = "abc"#, r#"let s = "abc\""#, r#"let s = "abc\"#, "let s = \"a\nb;"].iter() {
+            let mut lexer = Lexer::new(input);
+            for _ in 0..3 {
+                lexer.next_token();
+            }
+            let token = lexer.next_token();
+            assert_eq!( token.token, Token::Error(LexError::UnterminatedString), "input: {}", input );
+            assert_eq!( (token.span.start.line, token.span.start.column), (1, 9) );
+            assert_eq!( lexer.next_token().token, Token::EOF );
+        }
+    }
+
+    #[test]
+    fn skip_comments() {
+        let input = "// header\nlet x = 10 / 2; // trailing\r\n/* block\n /* nested */ still comment */ x /**/ * 3 //";
+        let tokens: Vec<Token> = std::iter::repeat_with({
+            let mut lexer = Lexer::new(input);
+            move || lexer.next_token().token
+        }).take_while(|token| *token != Token::EOF).collect();
+        assert_eq!( tokens, vec![
+            Token::Let, Token::Ident("x".to_owned()), Token::Assign, Token::Integer(10), Token::Slash, Token::Integer(2),
+            Token::Semicolon, Token::Ident("x".to_owned()), Token::Asterisk, Token::Integer(3),
+        ]);
+    }
+
+    #[test]
+    fn comment_trivia() {
+        let input = "x // one\n/* two /* three */ */ y";
+        let mut lexer = Lexer::new(input).with_trivia();
+        let answers = [
+            (Token::Ident("x".to_owned()), (1, 1), (1, 2)),
+            (Token::Comment("// one".to_owned()), (1, 3), (1, 9)),
+            (Token::Comment("/* two /* three */ */".to_owned()), (2, 1), (2, 22)),
+            (Token::Ident("y".to_owned()), (2, 23), (2, 24)),
+            (Token::EOF, (2, 24), (2, 24)),
+        ];
+        for (token, start, end) in answers.iter() {
+            let t = lexer.next_token();
+            assert_eq!( &t.token, token );
+            assert_eq!( (t.span.start.line, t.span.start.column), *start );
+            assert_eq!( (t.span.end.line, t.span.end.column), *end );
+        }
+    }
+
+    #[test]
+    fn read_unterminated_comment() {
+        for input in ["1 /* open", "1 /* outer /* inner */", "1 /*/", "1 /* a *"].iter() {
+            let mut lexer = Lexer::new(input);
+            assert_eq!( lexer.next_token().token, Token::Integer(1) );
+            let token = lexer.next_token();
+            assert_eq!( token.token, Token::Error(LexError::UnterminatedComment), "input: {}", input );
+            assert_eq!( token.span.start.column, 3 );
+            assert_eq!( lexer.next_token().token, Token::EOF );
+        }
+    }
+
+    #[test]
+    fn read_operators() {
+        let mut lexer = Lexer::new("a % b ** c <= d >= e && f || g & h | i ^ j << k >> l < m > n * o");
+        let expected = [
+            Token::Percent, Token::Power, Token::LTEq, Token::GTEq, Token::And, Token::Or,
+            Token::Ampersand, Token::Pipe, Token::Caret, Token::Shl, Token::Shr, Token::LT, Token::GT, Token::Asterisk,
+        ];
+        for token in expected.iter() {
+            assert!( matches!(lexer.next_token().token, Token::Ident(_)) );
+            assert_eq!( &lexer.next_token().token, token );
+        }
+        assert_eq!( lexer.next_token().token, Token::Ident("o".to_owned()) );
+        assert_eq!( lexer.next_token().token, Token::EOF );
+
+        let mut lexer = Lexer::new("x = 1; x += 2 -= 3 *= 4 /= 5 ** 6 */ 7");
+        let expected = [
+            Token::Ident("x".to_owned()), Token::Assign, Token::Integer(1), Token::Semicolon,
+            Token::Ident("x".to_owned()), Token::PlusAssign, Token::Integer(2), Token::MinusAssign, Token::Integer(3),
+            Token::AsteriskAssign, Token::Integer(4), Token::SlashAssign, Token::Integer(5), Token::Power, Token::Integer(6),
+            Token::Asterisk, Token::Slash, Token::Integer(7), Token::EOF,
+        ];
+        for token in expected.iter() {
+            assert_eq!( &lexer.next_token().token, token );
+        }
+    }
+
+    #[test]
+    fn read_float() {
+        let tests = [
+            ("2.75", Token::Float(2.75)),
+            ("0.5", Token::Float(0.5)),
+            ("1e-9", Token::Float(1e-9)),
+            ("1E+3", Token::Float(1000.0)),
+            ("2.5e3", Token::Float(2500.0)),
+            ("1e400", Token::Error(LexError::FloatOverflow("1e400".to_owned()))),
+            ("1e", Token::Error(LexError::MalformedFloat("1e".to_owned()))),
+            ("1e-", Token::Error(LexError::MalformedFloat("1e-".to_owned()))),
+        ];
+        for (input, expected) in tests.iter() {
+            let mut lexer = Lexer::new(input);
+            let token = lexer.next_token();
+            assert_eq!( &token.token, expected, "input: {}", input );
+            assert_eq!( token.span.end.offset, input.len() );
+            assert_eq!( lexer.next_token().token, Token::EOF );
+        }
+
+        // `.` の直後に数字がなければ小数点ではない
+        let mut lexer = Lexer::new("1.x");
+        assert_eq!( lexer.next_token().token, Token::Integer(1) );
+        assert_eq!( lexer.next_token().token, Token::Illegal );
+    }
+
+    #[test]
+    fn read_big_integer() {
+        let mut lexer = Lexer::new("9223372036854775807 9223372036854775808;");
+        assert_eq!( lexer.next_token().token, Token::Integer(i64::MAX) );
+        let big = lexer.next_token();
+        let expected = BigInt::from_str_radix("9223372036854775808", 10).unwrap();
+        assert_eq!( big.token, Token::BigInteger(Box::new(expected)) );
+        assert_eq!( (big.span.start.column, big.span.end.column), (21, 40) );
+        assert_eq!( lexer.next_token().token, Token::Semicolon );
+    }
+
+    #[test]
+    fn read_radix_and_separators() {
+        let big = BigInt::from_str_radix("ffffffffffffffffff", 16).unwrap();
+        let tests = [
+            ("0xFF", Token::Integer(255)),
+            ("0xdead_beef", Token::Integer(0xdead_beef)),
+            ("0o755", Token::Integer(0o755)),
+            ("0b1010", Token::Integer(10)),
+            ("0b_1", Token::Error(LexError::MisplacedSeparator("0b_1".to_owned()))),
+            ("1_000_000", Token::Integer(1_000_000)),
+            ("0", Token::Integer(0)),
+            ("1_000.000_1", Token::Float(1000.0001)),
+            ("1e1_0", Token::Float(1e10)),
+            ("0xff_ffff_ffff_ffff_ffff", Token::BigInteger(Box::new(big))),
+            ("0x", Token::Error(LexError::MissingDigits("0x".to_owned()))),
+            ("0o", Token::Error(LexError::MissingDigits("0o".to_owned()))),
+            ("0b2", Token::Error(LexError::InvalidDigit{ literal: "0b2".to_owned(), digit: '2', radix: 2 })),
+            ("0b1012", Token::Error(LexError::InvalidDigit{ literal: "0b1012".to_owned(), digit: '2', radix: 2 })),
+            ("0o78", Token::Error(LexError::InvalidDigit{ literal: "0o78".to_owned(), digit: '8', radix: 8 })),
+            ("0xfg_1", Token::Error(LexError::InvalidDigit{ literal: "0xfg_1".to_owned(), digit: 'g', radix: 16 })),
+            ("1__000", Token::Error(LexError::MisplacedSeparator("1__000".to_owned()))),
+            ("1_", Token::Error(LexError::MisplacedSeparator("1_".to_owned()))),
+            ("1_.5", Token::Error(LexError::MisplacedSeparator("1_.5".to_owned()))),
+        ];
+        for (input, expected) in tests.iter() {
+            let mut lexer = Lexer::new(input);
+            let token = lexer.next_token();
+            assert_eq!( &token.token, expected, "input: {}", input );
+            assert_eq!( token.span.end.offset, input.len(), "input: {}", input );
+            assert_eq!( lexer.next_token().token, Token::EOF );
+        }
+
+        let mut lexer = Lexer::new("0x10+0b1;");
+        assert_eq!( lexer.next_token().token, Token::Integer(16) );
+        assert_eq!( lexer.next_token().token, Token::Plus );
+        assert_eq!( lexer.next_token().token, Token::Integer(1) );
+        assert_eq!( lexer.next_token().token, Token::Semicolon );
+    }
+
     #[test]
     fn text_next_token() {
         let input = r#"let five = 5;
@@ -155,7 +605,7 @@ let add = fn(x, y) {
     x + y
 };
 let result = add(five, ten);
-!-/*5;
+!-/ *5;
 5 < 10 > 5;
 if (5 < 10) {
     return true;
@@ -164,7 +614,9 @@ if (5 < 10) {
 }
 "foo bar";
 10 == 10;
-10 != 9;"#;
+10 != 9;
+[1, 2];
+{"foo": "bar"}"#;
         let answers = vec![
             Token::Let,
             Token::Ident("five".to_owned()),
@@ -240,12 +692,48 @@ if (5 < 10) {
             Token::NotEq, 
             Token::Integer(9),
             Token::Semicolon,
+            Token::LBracket,
+            Token::Integer(1),
+            Token::Comma,
+            Token::Integer(2),
+            Token::RBracket,
+            Token::Semicolon,
+            Token::LBrace,
+            Token::String("foo".to_owned()),
+            Token::Colon,
+            Token::String("bar".to_owned()),
+            Token::RBrace,
             Token::EOF, 
         ];
 
         let mut lexer = Lexer::new(input);
         for answer in answers.iter() {
-            assert_eq!(&lexer.next_token(), answer);
+            assert_eq!(&lexer.next_token().token, answer);
+        }
+    }
+
+    #[test]
+    fn test_token_span() {
+        let input = "let x = 5;\n  \"あい\" == y;";
+        let answers = [
+            (Token::Let, (0, 1, 1), (3, 1, 4)),
+            (Token::Ident("x".to_owned()), (4, 1, 5), (5, 1, 6)),
+            (Token::Assign, (6, 1, 7), (7, 1, 8)),
+            (Token::Integer(5), (8, 1, 9), (9, 1, 10)),
+            (Token::Semicolon, (9, 1, 10), (10, 1, 11)),
+            (Token::String("あい".to_owned()), (13, 2, 3), (21, 2, 7)),
+            (Token::Eq, (22, 2, 8), (24, 2, 10)),
+            (Token::Ident("y".to_owned()), (25, 2, 11), (26, 2, 12)),
+            (Token::Semicolon, (26, 2, 12), (27, 2, 13)),
+            (Token::EOF, (27, 2, 13), (27, 2, 13)),
+        ];
+
+        let mut lexer = Lexer::new(input);
+        for (token, start, end) in answers.iter() {
+            let t = lexer.next_token();
+            assert_eq!( &t.token, token );
+            assert_eq!( t.span.start, Position { offset: start.0, line: start.1, column: start.2 } );
+            assert_eq!( t.span.end, Position { offset: end.0, line: end.1, column: end.2 } );
         }
     }
 
@@ -1,20 +1,58 @@
+pub mod span;
 pub mod token;
 pub mod lexer;
 pub mod ast;
 pub mod operator;
+pub mod bigint;
 pub mod parser;
 pub mod object;
 pub mod env;
 pub mod eval;
+pub mod code;
+pub mod compiler;
+pub mod vm;
+pub mod builtins;
+pub mod convert;
 pub mod repl;
 pub mod error;
+pub mod diagnostics;
 
 
+/// プログラムを実行する方式.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// AST を直接評価する (crate::eval::Evaluator)
+    Eval,
+    /// バイトコードにコンパイルして仮想機械で実行する (crate::compiler::Compiler, crate::vm::Vm)
+    Vm,
+}
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "eval" => Ok(Backend::Eval),
+            "vm"   => Ok(Backend::Vm),
+            _      => Err(format!("unknown backend `{}` (expected `eval` or `vm`)", s)),
+        }
+    }
+}
+
 pub fn evaluate(input: &str) -> Result<crate::object::Object, crate::error::MonkeyError> {
-    let mut env = eval::Evaluator::new();
+    evaluate_with(input, Backend::Eval)
+}
+
+/// 指定した方式でプログラムを実行する.
+pub fn evaluate_with(input: &str, backend: Backend) -> Result<crate::object::Object, crate::error::MonkeyError> {
     let l = lexer::Lexer::new(input);
     let mut p = parser::Parser::new(l);
     let program = p.parse_program()?;
 
-    env.eval(&program)
+    match backend {
+        Backend::Eval => eval::Evaluator::new().eval(&program),
+        Backend::Vm   => {
+            let bytecode = compiler::Compiler::new().compile(&program)?;
+            vm::Vm::new().run(&bytecode)
+        },
+    }
 }
@@ -1,14 +1,65 @@
-use std::{env, fs};
-use monkey_interpreter::{repl, evaluate};
+use std::{env, fs, process};
+use monkey_interpreter::{
+    Backend,
+    repl,
+    lexer::Lexer,
+    parser::Parser,
+    eval::Evaluator,
+    compiler::Compiler,
+    vm::Vm,
+    diagnostics::Diagnostic,
+};
+
+const USAGE: &str = "usage: monkey-interpreter [--backend=eval|vm] [FILE]";
 
 fn main() {
     eprintln!("This is the Monky programming language!");
+
+    let mut backend = Backend::Eval;
+    let mut path = None;
+    for arg in env::args().skip(1) {
+        if let Some(name) = arg.strip_prefix("--backend=") {
+            backend = name.parse().unwrap_or_else(|e| {
+                eprintln!("error: {}\n{}", e, USAGE);
+                process::exit(2);
+            });
+        } else if arg.starts_with("--") || path.is_some() {
+            eprintln!("error: unexpected argument `{}`\n{}", arg, USAGE);
+            process::exit(2);
+        } else {
+            path = Some(arg);
+        }
+    }
     
-    match env::args().nth(1) {
+    match path {
         Some(fp) => {
-            let input = fs::read_to_string(fp).unwrap();
-            evaluate(&input).unwrap();
+            let input = match fs::read_to_string(&fp) {
+                Ok(input) => input,
+                Err(e) => {
+                    eprintln!("error: failed to read {}: {}", fp, e);
+                    process::exit(1);
+                },
+            };
+
+            // 構文エラーは実行前にすべて報告する
+            let mut parser = Parser::new(Lexer::new(&input));
+            let (program, errors) = parser.parse_program_with_recovery();
+            if !errors.is_empty() {
+                for e in errors.iter() {
+                    eprint!("{}", Diagnostic::new(e, &input).with_path(&fp));
+                }
+                process::exit(1);
+            }
+
+            let result = match backend {
+                Backend::Eval => Evaluator::new().eval(&program),
+                Backend::Vm   => Compiler::new().compile(&program).and_then(|bytecode| Vm::new().run(&bytecode)),
+            };
+            if let Err(e) = result {
+                eprint!("{}", Diagnostic::new(&e, &input).with_path(&fp));
+                process::exit(1);
+            }
         },
-        None => repl::start().unwrap(),
+        None => repl::start(backend).unwrap(),
     }
 }
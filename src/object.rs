@@ -1,24 +1,63 @@
-use std::fmt;
-use crate::{ast, env::Environment};
+use std::{fmt, collections::HashMap};
+use std::{rc::Rc, cell::RefCell};
+use crate::{ast, env::Environment, builtins::Builtin, vm::Closure, bigint::BigInt};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ObjectType {
     Integer,
+    Float,
     Bool,
     Null,
+    String,
+    Array,
+    Hash,
+    Function,
+    Builtin,
+}
+impl fmt::Display for ObjectType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjectType::Integer => write!(f, "integer"),
+            ObjectType::Float   => write!(f, "float"),
+            ObjectType::Bool    => write!(f, "boolean"),
+            ObjectType::Null    => write!(f, "null"),
+            ObjectType::String  => write!(f, "string"),
+            ObjectType::Array   => write!(f, "array"),
+            ObjectType::Hash    => write!(f, "hash"),
+            ObjectType::Function => write!(f, "function"),
+            ObjectType::Builtin => write!(f, "builtin function"),
+        }
+    }
 }
 
 /// オブジェクト
 #[derive(Debug, Clone)]
 pub enum Object {
     String(String),
-    Integer(i32),
+    Integer(i64),
+    /// i64 に収まらない整数. 演算結果が i64 に収まれば Integer に戻す (Object::integer).
+    BigInteger(BigInt),
+    /// 浮動小数点数. 常に小数点か指数部を伴って表示される (`3.0`, `1e-9`, `NaN`, `inf`).
+    Float(f64),
     Bool(bool),
     Null,
-    ReturnValue(Box<Object>),
-    Function{parameters: Vec<ast::Expression>, body: ast::Statement, env: Environment},
+    Array(Vec<Object>),
+    Hash(HashTable),
+    /// 関数は定義された環境への参照を持つ. 呼び出すたびにその環境を外側とする新しい環境を作る.
+    Function{parameters: Vec<ast::Expression>, body: Box<ast::Statement>, env: Rc<RefCell<Environment>>},
+    Builtin(Builtin),
+    /// crate::vm::Vm が生成する関数
+    Closure(Rc<Closure>),
 }
 impl Object {
+    /// 多倍長整数を整数オブジェクトにする. i64 に収まれば Object::Integer になる.
+    pub fn integer(value: BigInt) -> Object {
+        match value.to_i64() {
+            Some(value) => Object::Integer(value),
+            None        => Object::BigInteger(value),
+        }
+    }
+
     pub fn is_truthy(&self) -> bool {
         match self {
             Object::Null => false,
@@ -26,14 +65,36 @@ impl Object {
             _ => true,
         }
     }
+
+    /// オブジェクトの型を返す.
+    pub fn type_of(&self) -> ObjectType {
+        match self {
+            Object::String(_)          => ObjectType::String,
+            Object::Integer(_)         => ObjectType::Integer,
+            Object::BigInteger(_)      => ObjectType::Integer,
+            Object::Float(_)           => ObjectType::Float,
+            Object::Bool(_)            => ObjectType::Bool,
+            Object::Null               => ObjectType::Null,
+            Object::Array(_)           => ObjectType::Array,
+            Object::Hash(_)            => ObjectType::Hash,
+            Object::Function{..}       => ObjectType::Function,
+            Object::Builtin(_)         => ObjectType::Builtin,
+            Object::Closure(_)         => ObjectType::Function,
+        }
+    }
 }
 impl PartialEq for Object {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Object::String(x), Object::String(y)) => x == y,
             (Object::Integer(x), Object::Integer(y)) => x == y,
+            (Object::BigInteger(x), Object::BigInteger(y)) => x == y,
+            (Object::Float(x), Object::Float(y)) => x == y,
             (Object::Bool(x), Object::Bool(y)) => x == y,
             (Object::Null, Object::Null) => true,
+            (Object::Array(x), Object::Array(y)) => x == y,
+            (Object::Hash(x), Object::Hash(y)) => x == y,
+            (Object::Builtin(x), Object::Builtin(y)) => x == y,
             // (Object::Function{..}, Object::Function{..}) => {
             //     format!("{}", self) == format!("{}", other)
             // },
@@ -47,15 +108,111 @@ impl fmt::Display for Object {
         match self {
             Object::String(s)          => write!(f, "{}", s),
             Object::Integer(value)     => write!(f, "{}", value),
+            Object::BigInteger(value)  => write!(f, "{}", value),
+            Object::Float(value)       => write!(f, "{:?}", value),
             Object::Bool(value)        => write!(f, "{}", value),
             Object::Null               => write!(f, "null"),
-            Object::ReturnValue(value) => write!(f, "{}", value),
+            Object::Array(elements)    => {
+                write!(f, "[{}]", elements.iter().map(|obj| format!("{}", obj)).collect::<Vec<_>>().join(", "))
+            },
+            Object::Hash(table)        => {
+                write!(f, "{{{}}}", table.iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect::<Vec<_>>().join(", ")
+                )
+            },
             Object::Function{parameters, body, ..} => {
                 write!(f, "fn({}){{{}}}", 
                     parameters.iter().map(|expr| format!("{}", expr)).collect::<Vec<_>>().join(","), 
                     body
                 )
             },
+            Object::Builtin(builtin) => write!(f, "builtin function {}", builtin.name),
+            Object::Closure(closure) => write!(f, "{}", closure.function.source),
+        }
+    }
+}
+
+/// ハッシュのキーとして使えるオブジェクト.
+/// 文字列, 整数, 真偽値のみがハッシュ可能である.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    String(String),
+    Integer(i64),
+    BigInteger(BigInt),
+    Bool(bool),
+}
+impl HashKey {
+    /// オブジェクトをハッシュキーに変換する. ハッシュ可能でなければその型を `Err` で返す.
+    pub fn from_object(obj: &Object) -> Result<HashKey, ObjectType> {
+        match obj {
+            Object::String(s)      => Ok(HashKey::String(s.to_owned())),
+            Object::Integer(value) => Ok(HashKey::Integer(*value)),
+            Object::BigInteger(value) => Ok(HashKey::BigInteger(value.clone())),
+            Object::Bool(value)    => Ok(HashKey::Bool(*value)),
+            obj                    => Err(obj.type_of()),
         }
     }
+
+    /// キーを元のオブジェクトに戻す.
+    pub fn to_object(&self) -> Object {
+        match self {
+            HashKey::String(s)      => Object::String(s.to_owned()),
+            HashKey::Integer(value) => Object::Integer(*value),
+            HashKey::BigInteger(value) => Object::BigInteger(value.clone()),
+            HashKey::Bool(value)    => Object::Bool(*value),
+        }
+    }
+}
+impl fmt::Display for HashKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_object())
+    }
+}
+
+/// ハッシュオブジェクトの中身. キーを挿入順に保持する.
+#[derive(Debug, Clone, Default)]
+pub struct HashTable {
+    entries: Vec<(HashKey, Object)>,
+    index: HashMap<HashKey, usize>,
+}
+impl HashTable {
+    pub fn new() -> Self {
+        HashTable { entries: Vec::new(), index: HashMap::new() }
+    }
+
+    /// キーと値の組を追加する. 既にキーが存在する場合は値だけを置き換え, 順序は変えない.
+    pub fn insert(&mut self, key: HashKey, value: Object) {
+        match self.index.get(&key) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            },
+        }
+    }
+
+    pub fn get(&self, key: &HashKey) -> Option<&Object> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// キーと値の組を挿入順に返すイテレータ.
+    pub fn iter(&self) -> impl Iterator<Item = &(HashKey, Object)> {
+        self.entries.iter()
+    }
+}
+impl PartialEq for HashTable {
+    /// 挿入順によらず, 同じキーと値の組を持つかどうかで比較する.
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self.iter().all(|(key, value)| other.get(key) == Some(value))
+    }
 }
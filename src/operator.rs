@@ -1,7 +1,7 @@
 use std::fmt;
 
 /// 前置演算子
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Prefix {
     Bang,
     Minus,
@@ -16,16 +16,26 @@ impl fmt::Display for Prefix {
 }
 
 /// 中置演算子
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Infix {
     Plus,
     Minus,
     Asterisk,
     Slash,
+    Percent,
+    /// 累乗. 右結合.
+    Power,
     Eq,
     NotEq,
     LT,
     GT,
+    LTEq,
+    GTEq,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
 }
 impl fmt::Display for Infix {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -34,10 +44,35 @@ impl fmt::Display for Infix {
             Infix::Minus    => write!(f, "-"),
             Infix::Asterisk => write!(f, "*"),
             Infix::Slash    => write!(f, "/"),
+            Infix::Percent  => write!(f, "%"),
+            Infix::Power    => write!(f, "**"),
             Infix::Eq       => write!(f, "=="),
             Infix::NotEq    => write!(f, "!="),
             Infix::LT       => write!(f, "<"),
             Infix::GT       => write!(f, ">"),
+            Infix::LTEq     => write!(f, "<="),
+            Infix::GTEq     => write!(f, ">="),
+            Infix::BitAnd   => write!(f, "&"),
+            Infix::BitOr    => write!(f, "|"),
+            Infix::BitXor   => write!(f, "^"),
+            Infix::Shl      => write!(f, "<<"),
+            Infix::Shr      => write!(f, ">>"),
+        }
+    }
+}
+
+/// 論理演算子. 左辺だけで結果が決まれば右辺を評価せず, 左辺の値をそのまま返す.
+/// そうでなければ右辺の値を返す.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Logical {
+    And,
+    Or,
+}
+impl fmt::Display for Logical {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Logical::And => write!(f, "&&"),
+            Logical::Or  => write!(f, "||"),
         }
     }
 }
@@ -46,12 +81,21 @@ impl fmt::Display for Infix {
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Precedence {
     Lowest,
+    LogicalOr,
+    LogicalAnd,
     Equals,
     LessGreater,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
     Sum,
     Product,
     Prefix,
+    /// `**` は前置演算子より強く結合する (`-2 ** 2` は `-(2 ** 2)`).
+    Power,
     Call,
+    Index,
 }
 
 #[cfg(test)]
@@ -63,5 +107,8 @@ mod tests {
         assert_eq!( operator::Precedence::Product, operator::Precedence::Product );
         assert!( operator::Precedence::Lowest < operator::Precedence::Equals );
         assert!( operator::Precedence::Call >= operator::Precedence::Prefix );
+        assert!( operator::Precedence::LogicalOr < operator::Precedence::LogicalAnd );
+        assert!( operator::Precedence::BitOr < operator::Precedence::BitXor );
+        assert!( operator::Precedence::Power > operator::Precedence::Prefix );
     }
 }
\ No newline at end of file
@@ -1,23 +1,36 @@
 use crate::{
     token::Token,
     lexer::Lexer,
-    ast,
+    ast::{self, Expression, ExpressionKind, Statement, StatementKind},
     operator::self,
     error::MonkeyError,
+    span::Span,
 };
 
 #[derive(Debug, Clone)]
 pub struct Parser<'a> {
     l: Lexer<'a>,
     cur_token: Token,
+    cur_span: Span,
     peek_token: Token,
+    peek_span: Span,
+    /// エラー回復モード. true のときブロック内の構文エラーを errors に記録してパースを続ける.
+    recover: bool,
+    errors: Vec<MonkeyError>,
+    /// 現在の関数の中で囲んでいるループの数. break と continue はループの中でしか使えない.
+    loop_depth: usize,
 }
 impl<'a> Parser<'a> {
     pub fn new(l: Lexer<'a>) -> Self {
         let mut p = Parser { 
             l, 
             cur_token: Token::Illegal, 
+            cur_span: Span::default(),
             peek_token: Token::Illegal,
+            peek_span: Span::default(),
+            recover: false,
+            errors: Vec::new(),
+            loop_depth: 0,
         };
         p.next_token();
         p.next_token();
@@ -26,7 +39,14 @@ impl<'a> Parser<'a> {
 
     fn next_token(&mut self) {
         self.cur_token = self.peek_token.clone();
-        self.peek_token = self.l.next_token();
+        self.cur_span = self.peek_span;
+        let mut next = self.l.next_token();
+        // Lexer::with_trivia で生成された字句解析器が渡されてもコメントは無視する
+        while let Token::Comment(_) = next.token {
+            next = self.l.next_token();
+        }
+        self.peek_token = next.token;
+        self.peek_span = next.span;
     }
 
     fn cur_token_is(&self, expected_token: Token) -> bool {
@@ -42,10 +62,7 @@ impl<'a> Parser<'a> {
             self.next_token();
             Ok(())
         } else {
-            Err(MonkeyError::UnexpectedToken {
-                expected: expected_token,
-                got: self.peek_token.clone()
-            })
+            Err(unexpected_token(expected_token, &self.peek_token, self.peek_span))
         }
     }
 
@@ -70,28 +87,137 @@ impl<'a> Parser<'a> {
         Ok(program)
     }
 
+    /// エラー回復モードでプログラム全体をパースする.
+    /// 構文エラーに出会うと次の文の先頭まで読み飛ばしてパースを続け,
+    /// パースできた文からなる部分的な AST と, 見つかったすべての構文エラーを返す.
+    pub fn parse_program_with_recovery(&mut self) -> (ast::Program, Vec<MonkeyError>) {
+        self.recover = true;
+        let mut program = ast::Program::new();
+
+        while !self.cur_token_is(Token::EOF) {
+            match self.parse_statement() {
+                Ok(stmt) => {
+                    program.statements.push(stmt);
+                    self.next_token();
+                },
+                Err(e) => {
+                    self.errors.push(e);
+                    self.synchronize();
+                    // トップレベルに対応する `{` のない `}` は読み飛ばす
+                    if self.cur_token_is(Token::RBrace) {
+                        self.next_token();
+                    }
+                },
+            }
+        }
+
+        self.recover = false;
+        (program, std::mem::take(&mut self.errors))
+    }
+
+    /// 構文エラーの後, 次の文の先頭と思われる位置まで読み飛ばす.
+    /// `;` の直後, 対応する `{` のない `}` の位置, または文を始めるキーワードの位置で止まる.
+    /// 途中の `{ ... }` はまとめて読み飛ばす. 対応する `{` のない `}` で止まる場合を除き, 少なくとも 1 トークンは進む.
+    /// その `}` は囲んでいるブロック (トップレベルでは parse_program_with_recovery) が読む.
+    fn synchronize(&mut self) {
+        let start = self.cur_span;
+        let mut depth = 0usize;
+
+        loop {
+            match self.cur_token {
+                Token::EOF => return,
+                Token::LBrace => depth += 1,
+                Token::RBrace if depth == 0 => break,
+                Token::RBrace => depth -= 1,
+                Token::Semicolon if depth == 0 => {
+                    self.next_token();
+                    break;
+                },
+                Token::Let | Token::Const | Token::Return | Token::While | Token::For | Token::Break | Token::Continue
+                    if depth == 0 && self.cur_span != start => break,
+                _ => {},
+            }
+            self.next_token();
+        }
+
+        if self.cur_span == start && !self.cur_token_is(Token::RBrace) {
+            self.next_token();
+        }
+    }
+
     /// 文をパースする
-    /// let 文, return 文, 式文のいずれかを判断し適切なメソッドを呼び出す
-    fn parse_statement(&mut self) -> Result<ast::Statement, MonkeyError> {
+    /// let 文, const 文, 代入文, return 文, while 文, for 文, break 文, continue 文, ブロック文, 式文のいずれかを判断し適切なメソッドを呼び出す
+    fn parse_statement(&mut self) -> Result<Statement, MonkeyError> {
         match self.cur_token {
+            Token::LBrace if !self.hash_literal_ahead() => self.parse_bare_block_statement(),
             Token::Let    => self.parse_let_statement(),
+            Token::Const  => self.parse_const_statement(),
+            Token::Ident(_) if self.peek_token_is(Token::Assign) || self.peek_token.compound_operator().is_some() => {
+                self.parse_assign_statement()
+            },
             Token::Return => self.parse_return_statement(),
+            Token::While  => self.parse_while_statement(),
+            Token::For    => self.parse_for_statement(),
+            Token::Break | Token::Continue => self.parse_loop_control_statement(),
             _                 => self.parse_expression_statement(),
         }
     }
 
+    /// 文の先頭の `{` がハッシュリテラルを始めるかどうかを先読みして判断する.
+    /// `{}` と, 括弧の外で `;` や `}` より先に `:` が現れるものはハッシュリテラル, それ以外はブロック文とする.
+    fn hash_literal_ahead(&self) -> bool {
+        if self.peek_token_is(Token::RBrace) {
+            return true;
+        }
+        let mut lexer = self.l.clone();
+        let mut token = self.peek_token.clone();
+        let mut depth = 0usize;
+        loop {
+            match token {
+                Token::Colon if depth == 0 => return true,
+                Token::Semicolon | Token::RBrace | Token::RParen | Token::RBracket if depth == 0 => return false,
+                Token::EOF => return false,
+                Token::LBrace | Token::LParen | Token::LBracket => depth += 1,
+                Token::RBrace | Token::RParen | Token::RBracket => depth -= 1,
+                _ => {},
+            }
+            token = lexer.next_token().token;
+        }
+    }
+
+    /// 文として書かれたブロックをパース. 後ろの `;` は省略できる.
+    fn parse_bare_block_statement(&mut self) -> Result<Statement, MonkeyError> {
+        let block = self.parse_block_statement()?;
+        if self.peek_token_is(Token::Semicolon) {
+            self.next_token();
+        }
+        Ok(block)
+    }
+
     /// let 文をパース
-    fn parse_let_statement(&mut self) -> Result<ast::Statement, MonkeyError> {
-        // let を飛ばして次に行く
+    fn parse_let_statement(&mut self) -> Result<Statement, MonkeyError> {
+        self.parse_binding_statement(|ident, value| StatementKind::Let{ident, value})
+    }
+
+    /// const 文をパース
+    fn parse_const_statement(&mut self) -> Result<Statement, MonkeyError> {
+        self.parse_binding_statement(|ident, value| StatementKind::Const{ident, value})
+    }
+
+    /// let 文と const 文に共通する `識別子 = 式` の部分をパースし, kind で文の種類を作る.
+    fn parse_binding_statement<F>(&mut self, kind: F) -> Result<Statement, MonkeyError>
+        where F: FnOnce(Expression, Expression) -> StatementKind
+    {
+        let start = self.cur_span;
+
+        // let または const を飛ばして次に行く
         self.next_token();
 
-        // let の次には識別子が来るはずなので, それを ast::Expression に変換する.
+        // 次には識別子が来るはずなので, それを ast::Expression に変換する.
         let ident = if let Token::Ident(ident) = &self.cur_token {
-            ast::Expression::Ident(ident.to_owned())
+            Expression::new(ExpressionKind::Ident(ident.to_owned()), self.cur_span)
         } else { 
-            return Err(MonkeyError::UnexpectedToken{
-                expected: Token::Ident("".to_owned()), got: self.cur_token.clone()
-            });
+            return Err(unexpected_token(Token::Ident("".to_owned()), &self.cur_token, self.cur_span));
         };
 
         // 識別子の次には等号が来るはず. なければエラーを返し終了.
@@ -104,15 +230,40 @@ impl<'a> Parser<'a> {
         let value = self.parse_expression(operator::Precedence::Lowest)?;
 
         // 次がセミコロンなら読み飛ばす. 
-        if !self.cur_token_is(Token::Semicolon) {
+        if self.peek_token_is(Token::Semicolon) {
             self.next_token();
         }
+        let end = if self.cur_token_is(Token::Semicolon) { self.cur_span } else { value.span };
         
-        Ok(ast::Statement::Let{ident, value})
+        Ok(Statement::new(kind(ident, value), start.to(end)))
+    }
+
+    /// 代入文をパース. 現在のトークンは代入先の識別子.
+    fn parse_assign_statement(&mut self) -> Result<Statement, MonkeyError> {
+        let start = self.cur_span;
+        let ident = match &self.cur_token {
+            Token::Ident(ident) => Expression::new(ExpressionKind::Ident(ident.to_owned()), self.cur_span),
+            _ => unreachable!(),
+        };
+
+        // `=` または複合代入演算子を飛ばす
+        self.next_token();
+        let op = self.cur_token.compound_operator();
+        self.next_token();
+
+        let value = self.parse_expression(operator::Precedence::Lowest)?;
+
+        if self.peek_token_is(Token::Semicolon) {
+            self.next_token();
+        }
+        let end = if self.cur_token_is(Token::Semicolon) { self.cur_span } else { value.span };
+
+        Ok(Statement::new(StatementKind::Assign{ident, op, value}, start.to(end)))
     }
 
     /// return 文をパース
-    fn parse_return_statement(&mut self) -> Result<ast::Statement, MonkeyError> {
+    fn parse_return_statement(&mut self) -> Result<Statement, MonkeyError> {
+        let start = self.cur_span;
         self.next_token();
 
         // 戻り値を取得
@@ -120,36 +271,113 @@ impl<'a> Parser<'a> {
 
         self.expect_peek(Token::Semicolon)?;
 
-        Ok(ast::Statement::Return(value))
+        Ok(Statement::new(StatementKind::Return(value), start.to(self.cur_span)))
+    }
+
+    /// while 文をパース
+    fn parse_while_statement(&mut self) -> Result<Statement, MonkeyError> {
+        let start = self.cur_span;
+        self.expect_peek(Token::LParen)?;
+        let condition = self.parse_expression(operator::Precedence::Lowest)?;
+        if !self.cur_token_is(Token::RParen) {
+            return Err(unexpected_token(Token::RParen, &self.cur_token, self.cur_span));
+        };
+
+        self.expect_peek(Token::LBrace)?;
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+        let body = body?;
+
+        if self.peek_token_is(Token::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(Statement::new(StatementKind::While{condition, body: Box::new(body)}, start.to(self.cur_span)))
+    }
+
+    /// for 文をパース
+    fn parse_for_statement(&mut self) -> Result<Statement, MonkeyError> {
+        let start = self.cur_span;
+        self.expect_peek(Token::LParen)?;
+        self.next_token();
+
+        let ident = if let Token::Ident(ident) = &self.cur_token {
+            Expression::new(ExpressionKind::Ident(ident.to_owned()), self.cur_span)
+        } else {
+            return Err(unexpected_token(Token::Ident("".to_owned()), &self.cur_token, self.cur_span));
+        };
+
+        self.expect_peek(Token::In)?;
+        self.next_token();
+        let iterable = self.parse_expression(operator::Precedence::Lowest)?;
+        self.expect_peek(Token::RParen)?;
+
+        self.expect_peek(Token::LBrace)?;
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+        let body = body?;
+
+        if self.peek_token_is(Token::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(Statement::new(StatementKind::For{ident, iterable, body: Box::new(body)}, start.to(self.cur_span)))
+    }
+
+    /// break 文と continue 文をパース
+    fn parse_loop_control_statement(&mut self) -> Result<Statement, MonkeyError> {
+        let start = self.cur_span;
+        if self.loop_depth == 0 {
+            return Err(MonkeyError::OutsideLoop(self.cur_token.clone(), start));
+        }
+        let kind = match self.cur_token {
+            Token::Break => StatementKind::Break,
+            _            => StatementKind::Continue,
+        };
+
+        if self.peek_token_is(Token::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(Statement::new(kind, start.to(self.cur_span)))
     }
 
     /// 式文をパース
-    fn parse_expression_statement(&mut self) -> Result<ast::Statement, MonkeyError> {
+    fn parse_expression_statement(&mut self) -> Result<Statement, MonkeyError> {
         let expr = self.parse_expression(operator::Precedence::Lowest)?;
+        let mut span = expr.span;
 
         if self.peek_token_is(Token::Semicolon) {
             self.next_token();
+            span = span.to(self.cur_span);
         }
 
-        Ok(ast::Statement::Expression(expr))
+        Ok(Statement::new(StatementKind::Expression(expr), span))
     }
 
     /// lexer をパースして ast::Expression を生成する.
     /// 呼び出し時のカーソル位置が読み出す expr の先頭で, このメソッド終了時には expr の最後のトークンにいる.
-    fn parse_expression(&mut self, precedence: operator::Precedence) -> Result<ast::Expression, MonkeyError> {
+    fn parse_expression(&mut self, precedence: operator::Precedence) -> Result<Expression, MonkeyError> {
         // 単独の式または前置演算子
+        let span = self.cur_span;
         let mut left = match &self.cur_token {
-            Token::Ident(ident)   => ast::Expression::Ident(ident.to_owned()),
-            Token::String(s)      => ast::Expression::String(s.to_owned()),
-            Token::Integer(value) => ast::Expression::Integer(*value),
-            Token::True       => ast::Expression::Bool(true),
-            Token::False      => ast::Expression::Bool(false),
+            Token::Ident(ident)   => Expression::new(ExpressionKind::Ident(ident.to_owned()), span),
+            Token::String(s)      => Expression::new(ExpressionKind::String(s.to_owned()), span),
+            Token::Integer(value) => Expression::new(ExpressionKind::Integer(*value), span),
+            Token::BigInteger(value) => Expression::new(ExpressionKind::BigInteger(*value.clone()), span),
+            Token::Float(value)   => Expression::new(ExpressionKind::Float(*value), span),
+            Token::True       => Expression::new(ExpressionKind::Bool(true), span),
+            Token::False      => Expression::new(ExpressionKind::Bool(false), span),
             Token::Bang       => self.parse_prefix_expression()?,
             Token::Minus      => self.parse_prefix_expression()?,
             Token::LParen     => self.parse_grouped_expression()?,
             Token::If         => self.parse_if_expression()?,
             Token::Function   => self.parse_function_literal()?,
-            token             => { return Err(MonkeyError::InvalidToken(token.clone())); },
+            Token::LBracket   => self.parse_array_literal()?,
+            Token::LBrace     => self.parse_hash_literal()?,
+            token             => { return Err(invalid_token(token, span)); },
         };
         
         // 次に中置演算子が来る場合はここで処理する
@@ -163,7 +391,19 @@ impl<'a> Parser<'a> {
                 Token::NotEq    => { self.next_token(); left = self.parse_infix_expression(left)?; },
                 Token::LT       => { self.next_token(); left = self.parse_infix_expression(left)?; },
                 Token::GT       => { self.next_token(); left = self.parse_infix_expression(left)?; },
+                Token::Percent  => { self.next_token(); left = self.parse_infix_expression(left)?; },
+                Token::Power    => { self.next_token(); left = self.parse_infix_expression(left)?; },
+                Token::LTEq     => { self.next_token(); left = self.parse_infix_expression(left)?; },
+                Token::GTEq     => { self.next_token(); left = self.parse_infix_expression(left)?; },
+                Token::Ampersand => { self.next_token(); left = self.parse_infix_expression(left)?; },
+                Token::Pipe     => { self.next_token(); left = self.parse_infix_expression(left)?; },
+                Token::Caret    => { self.next_token(); left = self.parse_infix_expression(left)?; },
+                Token::Shl      => { self.next_token(); left = self.parse_infix_expression(left)?; },
+                Token::Shr      => { self.next_token(); left = self.parse_infix_expression(left)?; },
+                Token::And      => { self.next_token(); left = self.parse_logical_expression(left)?; },
+                Token::Or       => { self.next_token(); left = self.parse_logical_expression(left)?; },
                 Token::LParen   => { self.next_token(); left = self.parse_call_expression(left)?; },
+                Token::LBracket => { self.next_token(); left = self.parse_index_expression(left)?; },
                 _               => { return Ok(left); },
             }
         }
@@ -171,22 +411,26 @@ impl<'a> Parser<'a> {
         Ok(left)
     }
     
-    fn parse_grouped_expression(&mut self) -> Result<ast::Expression, MonkeyError> {
+    fn parse_grouped_expression(&mut self) -> Result<Expression, MonkeyError> {
+        let start = self.cur_span;
         self.next_token();
 
-        let exp = self.parse_expression(operator::Precedence::Lowest)?;
+        let mut exp = self.parse_expression(operator::Precedence::Lowest)?;
         self.expect_peek(Token::RParen)?;
 
+        // 括弧を含めた範囲をこの式の範囲とする
+        exp.span = start.to(self.cur_span);
         Ok(exp)
     }
 
-    fn parse_if_expression(&mut self) -> Result<ast::Expression, MonkeyError> {
+    fn parse_if_expression(&mut self) -> Result<Expression, MonkeyError> {
+        let start = self.cur_span;
         self.expect_peek(Token::LParen)?;
         let condition = Box::new(
             self.parse_expression(operator::Precedence::Lowest)?
         );
         if !self.cur_token_is(Token::RParen) {
-            return Err(MonkeyError::UnexpectedToken{expected: Token::RParen, got: self.cur_token.clone()});
+            return Err(unexpected_token(Token::RParen, &self.cur_token, self.cur_span));
         };
 
         self.expect_peek(Token::LBrace)?;
@@ -200,22 +444,32 @@ impl<'a> Parser<'a> {
             Some(Box::new(alt))
         } else { None };
 
-        Ok(ast::Expression::If{condition, consequence, alternative})
+        Ok(Expression::new(
+            ExpressionKind::If{condition, consequence, alternative},
+            start.to(self.cur_span),
+        ))
     }
 
-    fn parse_function_literal(&mut self) -> Result<ast::Expression, MonkeyError> {
+    fn parse_function_literal(&mut self) -> Result<Expression, MonkeyError> {
+        let start = self.cur_span;
+
         // Token::Function に続いて Token::LParen が来るはず
         self.expect_peek(Token::LParen)?;
         let parameters = self.parse_function_parameters()?;
 
         // 引数リストが終わったら Token::LBrace に続いて関数の本体が来るかず
+        // 関数の外側のループは本体からは見えない
         self.expect_peek(Token::LBrace)?;
-        let body = self.parse_block_statement()?;
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let body = self.parse_block_statement();
+        self.loop_depth = loop_depth;
+        let body = body?;
 
-        Ok(ast::Expression::Function{parameters, body: Box::new(body)})
+        let span = start.to(body.span);
+        Ok(Expression::new(ExpressionKind::Function{parameters, body: Box::new(body)}, span))
     }
 
-    fn parse_function_parameters(&mut self) -> Result<Vec<ast::Expression>, MonkeyError> {
+    fn parse_function_parameters(&mut self) -> Result<Vec<Expression>, MonkeyError> {
         let mut idents = Vec::new();
 
         if self.peek_token_is(Token::RParen) {
@@ -228,8 +482,8 @@ impl<'a> Parser<'a> {
 
             while {
                 if let Token::Ident(ident) = &self.cur_token {
-                    idents.push( ast::Expression::Ident(ident.to_owned()) );
-                } else { return Err(MonkeyError::InvalidToken(self.cur_token.clone())); }
+                    idents.push( Expression::new(ExpressionKind::Ident(ident.to_owned()), self.cur_span) );
+                } else { return Err(invalid_token(&self.cur_token, self.cur_span)); }
 
                 self.peek_token_is(Token::Comma)
             } {
@@ -243,56 +497,115 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_call_expression(&mut self, function: ast::Expression) -> Result<ast::Expression, MonkeyError> {
-        let mut arguments = Vec::new();
+    fn parse_call_expression(&mut self, function: Expression) -> Result<Expression, MonkeyError> {
+        let arguments = self.parse_expression_list(Token::RParen)?;
 
-        if self.peek_token_is(Token::RParen) {
+        let span = function.span.to(self.cur_span);
+        Ok(Expression::new(ExpressionKind::Call{function: Box::new(function), arguments}, span))
+    }
+
+    fn parse_array_literal(&mut self) -> Result<Expression, MonkeyError> {
+        let start = self.cur_span;
+        let elements = self.parse_expression_list(Token::RBracket)?;
+
+        Ok(Expression::new(ExpressionKind::Array(elements), start.to(self.cur_span)))
+    }
+
+    /// ハッシュリテラルをパースする.
+    /// ブロック文は if 式や関数リテラルの直後でのみ parse_block_statement が読むので,
+    /// 式の先頭に現れる `{` は常にハッシュリテラルの開始である.
+    fn parse_hash_literal(&mut self) -> Result<Expression, MonkeyError> {
+        let start = self.cur_span;
+        let mut pairs = Vec::new();
+
+        while !self.peek_token_is(Token::RBrace) {
+            self.next_token();
+            let key = self.parse_expression(operator::Precedence::Lowest)?;
+            self.expect_peek(Token::Colon)?;
+            self.next_token();
+            let value = self.parse_expression(operator::Precedence::Lowest)?;
+            pairs.push((key, value));
+
+            if !self.peek_token_is(Token::RBrace) {
+                self.expect_peek(Token::Comma)?;
+            }
+        }
+        self.expect_peek(Token::RBrace)?;
+
+        Ok(Expression::new(ExpressionKind::Hash(pairs), start.to(self.cur_span)))
+    }
+
+    fn parse_index_expression(&mut self, left: Expression) -> Result<Expression, MonkeyError> {
+        self.next_token();
+        let index = self.parse_expression(operator::Precedence::Lowest)?;
+        self.expect_peek(Token::RBracket)?;
+
+        let span = left.span.to(self.cur_span);
+        Ok(Expression::new(ExpressionKind::Index{left: Box::new(left), index: Box::new(index)}, span))
+    }
+
+    /// カンマ区切りの式の列をパースする.
+    /// 呼び出し時のカーソル位置は開き括弧で, このメソッド終了時には閉じ括弧 `end` にいる.
+    fn parse_expression_list(&mut self, end: Token) -> Result<Vec<Expression>, MonkeyError> {
+        let mut list = Vec::new();
+
+        if self.peek_token_is(end.clone()) {
             self.next_token();
         } else {
             self.next_token();
             while {
-                arguments.push(self.parse_expression(operator::Precedence::Lowest)?);
+                list.push(self.parse_expression(operator::Precedence::Lowest)?);
                 self.peek_token_is(Token::Comma)
             } {
                 self.next_token();
                 self.next_token();
             }
 
-            self.expect_peek(Token::RParen)?;
+            self.expect_peek(end)?;
         }
 
-        Ok(ast::Expression::Call{function: Box::new(function), arguments})
+        Ok(list)
     }
 
-    fn parse_block_statement(&mut self) -> Result<ast::Statement, MonkeyError> {
+    fn parse_block_statement(&mut self) -> Result<Statement, MonkeyError> {
+        let start = self.cur_span;
         self.next_token();
 
         let mut blocks = Vec::new();
         while !self.cur_token_is(Token::RBrace) && !self.cur_token_is(Token::EOF) {
-            blocks.push(
-                self.parse_statement()?
-            );
-            self.next_token();
+            match self.parse_statement() {
+                Ok(stmt) => {
+                    blocks.push(stmt);
+                    self.next_token();
+                },
+                Err(e) if self.recover => {
+                    self.errors.push(e);
+                    self.synchronize();
+                },
+                Err(e) => { return Err(e); },
+            }
         }
 
-        Ok(ast::Statement::Block(blocks))
+        Ok(Statement::new(StatementKind::Block(blocks), start.to(self.cur_span)))
     }
 
-    fn parse_prefix_expression(&mut self) -> Result<ast::Expression, MonkeyError> {
+    fn parse_prefix_expression(&mut self) -> Result<Expression, MonkeyError> {
+        let start = self.cur_span;
         let op = match self.cur_token {
             Token::Bang  => operator::Prefix::Bang,
             Token::Minus => operator::Prefix::Minus,
-            _            => { return Err(MonkeyError::InvalidToken(self.cur_token.clone())); },
+            _            => { return Err(MonkeyError::InvalidToken(self.cur_token.clone(), self.cur_span)); },
         };
 
         self.next_token();
 
         let right = self.parse_expression(operator::Precedence::Prefix)?;
 
-        Ok(ast::Expression::Prefix{op, right: Box::new(right)})
+        let span = start.to(right.span);
+        Ok(Expression::new(ExpressionKind::Prefix{op, right: Box::new(right)}, span))
     }
 
-    fn parse_infix_expression(&mut self, left: ast::Expression) -> Result<ast::Expression, MonkeyError> {
+    fn parse_infix_expression(&mut self, left: Expression) -> Result<Expression, MonkeyError> {
         let op = match self.cur_token {
             Token::Plus     => operator::Infix::Plus,
             Token::Minus    => operator::Infix::Minus,
@@ -302,14 +615,59 @@ impl<'a> Parser<'a> {
             Token::NotEq    => operator::Infix::NotEq,
             Token::LT       => operator::Infix::LT,
             Token::GT       => operator::Infix::GT,
-            _               => { return Err(MonkeyError::InvalidToken(self.cur_token.clone())); },
+            Token::Percent  => operator::Infix::Percent,
+            Token::Power    => operator::Infix::Power,
+            Token::LTEq     => operator::Infix::LTEq,
+            Token::GTEq     => operator::Infix::GTEq,
+            Token::Ampersand => operator::Infix::BitAnd,
+            Token::Pipe     => operator::Infix::BitOr,
+            Token::Caret    => operator::Infix::BitXor,
+            Token::Shl      => operator::Infix::Shl,
+            Token::Shr      => operator::Infix::Shr,
+            _               => { return Err(MonkeyError::InvalidToken(self.cur_token.clone(), self.cur_span)); },
+        };
+
+        // `**` は右結合なので, 右辺は一段低い優先順位で読み次の `**` まで取り込む
+        let precedence = match op {
+            operator::Infix::Power => operator::Precedence::Prefix,
+            _                      => self.cur_precedence(),
+        };
+        self.next_token();
+        let right = self.parse_expression(precedence)?;
+
+        let span = left.span.to(right.span);
+        Ok(Expression::new(ExpressionKind::Infix{op, left: Box::new(left), right: Box::new(right)}, span))
+    }
+
+    fn parse_logical_expression(&mut self, left: Expression) -> Result<Expression, MonkeyError> {
+        let op = match self.cur_token {
+            Token::And => operator::Logical::And,
+            Token::Or  => operator::Logical::Or,
+            _          => { return Err(MonkeyError::InvalidToken(self.cur_token.clone(), self.cur_span)); },
         };
 
         let precedence = self.cur_precedence();
         self.next_token();
         let right = self.parse_expression(precedence)?;
 
-        Ok(ast::Expression::Infix{op, left: Box::new(left), right: Box::new(right)})
+        let span = left.span.to(right.span);
+        Ok(Expression::new(ExpressionKind::Logical{op, left: Box::new(left), right: Box::new(right)}, span))
+    }
+}
+
+/// 期待と異なるトークンに出会ったときのエラー. 字句解析のエラーであればそちらを報告する.
+fn unexpected_token(expected: Token, got: &Token, span: Span) -> MonkeyError {
+    match got {
+        Token::Error(e) => MonkeyError::Lexical(e.clone(), span),
+        got             => MonkeyError::UnexpectedToken{expected, got: got.clone(), span},
+    }
+}
+
+/// 式を始められないトークンに出会ったときのエラー. 字句解析のエラーであればそちらを報告する.
+fn invalid_token(got: &Token, span: Span) -> MonkeyError {
+    match got {
+        Token::Error(e) => MonkeyError::Lexical(e.clone(), span),
+        got             => MonkeyError::InvalidToken(got.clone(), span),
     }
 }
 
@@ -327,7 +685,7 @@ let x = 5;
 let y = 10;
 let foobar = 838383;"#;
 
-        let l = Lexer::new(&input);
+        let l = Lexer::new(input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
 
@@ -335,7 +693,7 @@ let foobar = 838383;"#;
             panic!("program.statements does not contain 3 statements. got {}",
                 program.statements.len());
         }
-        let expected_identifier = vec![
+        let expected_identifier = [
             "x",
             "y",
             "foobar",
@@ -347,8 +705,8 @@ let foobar = 838383;"#;
     }
 
     fn test_let_statement(stmt: &ast::Statement, expected_name: &str) {
-        if let ast::Statement::Let{ident,..} = stmt {
-            if let ast::Expression::Ident(ident) = ident {
+        if let ast::StatementKind::Let{ident,..} = &stmt.kind {
+            if let ast::ExpressionKind::Ident(ident) = &ident.kind {
                 assert_eq!( ident, expected_name );
             }
         } else {
@@ -363,7 +721,7 @@ return 5;
 return 10;
 return 993322;"#;
 
-        let l = Lexer::new(&input);
+        let l = Lexer::new(input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
 
@@ -373,7 +731,7 @@ return 993322;"#;
         }
 
         for stmt in program.statements.iter() {
-            if let ast::Statement::Return(_) = stmt {
+            if let ast::StatementKind::Return(_) = stmt.kind {
             } else {
                 panic!("expected Return, but got {:?}", &stmt);
             }
@@ -384,7 +742,7 @@ return 993322;"#;
     fn test_identifier_expression() {
         let input = "foobar;";
 
-        let l = Lexer::new(&input);
+        let l = Lexer::new(input);
         let mut p = Parser::new(l);
         let program = p.parse_program().expect("Failed to parse input!");
 
@@ -394,8 +752,8 @@ return 993322;"#;
         }
 
         let stmt = &program.statements[0];
-        if let ast::Statement::Expression(expr) = stmt {
-            if let ast::Expression::Ident(ident) = expr {
+        if let ast::StatementKind::Expression(expr) = &stmt.kind {
+            if let ast::ExpressionKind::Ident(ident) = &expr.kind {
                 assert_eq!(ident, "foobar");
             } else { panic!("Incorrect expression"); }
         } else {
@@ -407,7 +765,7 @@ return 993322;"#;
     fn test_integer_literal_expression() {
         let input = "5;";
 
-        let l = Lexer::new(&input);
+        let l = Lexer::new(input);
         let mut p = Parser::new(l);
         let program = p.parse_program().expect("Failed to parse input!");
 
@@ -417,8 +775,8 @@ return 993322;"#;
         }
 
         let stmt = &program.statements[0];
-        if let ast::Statement::Expression(expr) = stmt {
-            if let ast::Expression::Integer(value) = expr {
+        if let ast::StatementKind::Expression(expr) = &stmt.kind {
+            if let ast::ExpressionKind::Integer(value) = &expr.kind {
                 assert_eq!(value, &5);
             } else { panic!("Incorrect expression"); }
         } else {
@@ -428,11 +786,19 @@ return 993322;"#;
 
     #[test]
     fn operator_precedence() {
-        let inputs = [ "5 + 5 * 10;", "- a * b", ];
-        let answers = [ "(5+(5*10))", "((-a)*b)", ];
+        let inputs = [
+            "5 + 5 * 10;", "- a * b", "a % b + c", "a ** b ** c", "-a ** b", "a ** -b * c", "a * b ** c",
+            "a || b && c", "a && b || c", "a == b && c < d", "a <= b == c >= d",
+            "a | b ^ c & d", "a & b == c", "a << b + c", "a < b << c", "a >> b | c << d",
+        ];
+        let answers = [
+            "(5+(5*10))", "((-a)*b)", "((a%b)+c)", "(a**(b**c))", "(-(a**b))", "((a**(-b))*c)", "(a*(b**c))",
+            "(a||(b&&c))", "((a&&b)||c)", "((a==b)&&(c<d))", "((a<=b)==(c>=d))",
+            "(a|(b^(c&d)))", "((a&b)==c)", "(a<<(b+c))", "(a<(b<<c))", "((a>>b)|(c<<d))",
+        ];
 
         for (input, answer) in inputs.iter().zip(answers.iter()) {
-            let l = Lexer::new(&input);
+            let l = Lexer::new(input);
             let mut p = Parser::new(l);
             let program = p.parse_program().expect("Failed to parse input!");
 
@@ -442,7 +808,7 @@ return 993322;"#;
             }
     
             let stmt = &program.statements[0];
-            if let ast::Statement::Expression(expr) = stmt {
+            if let ast::StatementKind::Expression(expr) = &stmt.kind {
                 assert_eq!(&format!("{}", &expr), answer);
             } else {
                 panic!("Incorrect statement");
@@ -465,6 +831,14 @@ return 993322;"#;
             ("fn(x, y) { x + y; }", "fn(x,y){(x+y);};"),
             ("add(1, 2*3);", "add(1,(2*3));"),
             ("add(1, minus(4, -1));", "add(1,minus(4,(-1)));"),
+            ("[1, 2 * 2, 3 + 3]", "[1,(2*2),(3+3)];"),
+            ("[]", "[];"),
+            ("a * [1, 2, 3, 4][b * c] * d", "((a*([1,2,3,4][(b*c)]))*d);"),
+            ("add(a * b[2], b[1], 2 * [1, 2][1])", "add((a*(b[2])),(b[1]),(2*([1,2][1])));"),
+            ("{}", "{};"),
+            ("{\"one\": 1, \"two\": 2 * 3,}", "{one:1,two:(2*3)};"),
+            ("{1: true, x + 1: [2]}[1]", "({1:true,(x+1):[2]}[1]);"),
+            ("if (x) { {\"a\": 1} }", "if(x){{a:1};};"),
         ];
 
         for (input, answer) in problem.iter() {
@@ -482,4 +856,248 @@ return 993322;"#;
             eprintln!(" ... ok!");
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_node_span() {
+        let input = "let x = 1;\nadd(x, -2 * 3);";
+
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().expect("Failed to parse");
+
+        let spans: Vec<_> = program.statements.iter()
+            .map(|stmt| (stmt.span.start.offset, stmt.span.end.offset))
+            .collect();
+        assert_eq!( spans, vec![(0, 10), (11, 26)] );
+
+        if let ast::StatementKind::Expression(expr) = &program.statements[1].kind {
+            assert_eq!( (expr.span.start.line, expr.span.start.column), (2, 1) );
+            assert_eq!( (expr.span.end.line, expr.span.end.column), (2, 15) );
+            if let ast::ExpressionKind::Call{arguments, ..} = &expr.kind {
+                let arg = &arguments[1];
+                assert_eq!( (arg.span.start.column, arg.span.end.column), (8, 14) );
+            } else { panic!("expected call expression"); }
+        } else { panic!("expected expression statement"); }
+    }
+
+    #[test]
+    fn test_error_span() {
+        let input = "let x = 1;\nreturn x }";
+
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let err = p.parse_program().unwrap_err();
+        let span = err.span();
+        assert_eq!( (span.start.line, span.start.column), (2, 10) );
+    }
+
+    #[test]
+    fn test_bare_block() {
+        let tests = [
+            ("{ let z = 1; } puts(z);", vec!["let z = 1;", "puts(z);"]),
+            ("{ x }; { { 1 } }", vec!["x;", "1;"]),
+            ("{ f({1: 2}); x = [1] }", vec!["f({1:2});x = [1];"]),
+            // `{}` と `{ キー: 値 }` はハッシュリテラルの式文
+            ("{}", vec!["{};"]),
+            ("{1: 2}[1]; {\"a\" + f(x): {}}", vec!["({1:2}[1]);", "{(a+f(x)):{}};"]),
+        ];
+        for (input, expected) in tests.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().expect("Failed to parse input!");
+            let got: Vec<_> = program.statements.iter().map(|stmt| format!("{}", stmt)).collect();
+            assert_eq!( &got, expected, "input: {}", input );
+        }
+
+        let program = Parser::new(Lexer::new("{ let z = 1; } { {} }")).parse_program().unwrap();
+        assert!( program.statements.iter().all(|stmt| matches!(stmt.kind, ast::StatementKind::Block(_))) );
+    }
+
+    #[test]
+    fn test_while() {
+        use crate::{token::Token, error::MonkeyError};
+
+        let tests = [
+            ("while (x < 10) { let x = x + 1; }", "while((x<10)){let x = (x+1);}"),
+            ("while (true) { if (x) { break; } continue }", "while(true){if(x){break;};continue;}"),
+            ("while (a) { while (b) { break; } break; };", "while(a){while(b){break;}break;}"),
+            ("while (a) { let f = fn() { while (b) { continue; } }; }", "while(a){let f = fn(){while(b){continue;}};}"),
+        ];
+        for (input, expected) in tests.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().expect("Failed to parse input!");
+            assert_eq!( program.statements.len(), 1, "input: {}", input );
+            assert_eq!( &format!("{}", program.statements[0]), expected );
+        }
+
+        let errors = [
+            ("break;", Token::Break, 1),
+            ("if (x) { continue; }", Token::Continue, 10),
+            ("while (x) { let f = fn() { break; }; }", Token::Break, 28),
+            ("while (x) { 1 }; break", Token::Break, 18),
+        ];
+        for (input, keyword, column) in errors.iter() {
+            match Parser::new(Lexer::new(input)).parse_program() {
+                Err(MonkeyError::OutsideLoop(got, span)) => {
+                    assert_eq!( (&got, span.start.column), (keyword, *column), "input: {}", input );
+                },
+                result => panic!("expected OutsideLoop for {}, but got {:?}", input, result),
+            }
+        }
+    }
+
+    #[test]
+    fn test_assign() {
+        let tests = [
+            ("x = 1;", "x = 1;"),
+            ("x += y * 2", "x += (y*2);"),
+            ("x -= 1; x *= 2; x /= 3;", "x -= 1;x *= 2;x /= 3;"),
+            ("while (i < 3) { i = i + 1; }", "while((i<3)){i = (i+1);}"),
+            ("x == 1", "(x==1);"),
+            // ブロックの最後の代入文は `;` を省略できる
+            ("while (i < 3) { i = i + 1 } puts(i);", "while((i<3)){i = (i+1);}puts(i);"),
+            ("if (true) { i += 1 } i", "if(true){i += 1;};i;"),
+        ];
+        for (input, expected) in tests.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().expect("Failed to parse input!");
+            let got: String = program.statements.iter().map(|stmt| format!("{}", stmt)).collect();
+            assert_eq!( &got, expected, "input: {}", input );
+        }
+
+        let program = Parser::new(Lexer::new("  count += 1;")).parse_program().unwrap();
+        let span = program.statements[0].span;
+        assert_eq!( (span.start.column, span.end.column), (3, 14) );
+
+        for input in ["x = ;", "1 = 2", "x + = 1", "let x += 1;"].iter() {
+            assert!( Parser::new(Lexer::new(input)).parse_program().is_err(), "input: {}", input );
+        }
+    }
+
+    #[test]
+    fn test_const() {
+        let tests = [
+            ("const x = 1;", "const x = 1;"),
+            ("const f = fn(a) { a * 2 }", "const f = fn(a){(a*2);};"),
+            ("const x = 1; let y = x;", "const x = 1;let y = x;"),
+            // ブロックの最後の束縛は `;` を省略できる
+            ("if (true) { const x = 1 } x", "if(true){const x = 1;};x;"),
+            ("while (a) { let y = 2 } puts(y);", "while(a){let y = 2;}puts(y);"),
+        ];
+        for (input, expected) in tests.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().expect("Failed to parse input!");
+            let got: String = program.statements.iter().map(|stmt| format!("{}", stmt)).collect();
+            assert_eq!( &got, expected, "input: {}", input );
+        }
+
+        for input in ["const = 1;", "const x;", "const x += 1;", "const 1 = 2;"].iter() {
+            assert!( Parser::new(Lexer::new(input)).parse_program().is_err(), "input: {}", input );
+        }
+    }
+
+    #[test]
+    fn test_for() {
+        use crate::{token::Token, error::MonkeyError};
+
+        let tests = [
+            ("for (x in xs) { puts(x); }", "for(x in xs){puts(x);}"),
+            ("for (c in \"ab\" + s) { if (c) { break; } continue; };", "for(c in (ab+s)){if(c){break;};continue;}"),
+            ("for (i in range(0, 10, 2)) { for (j in [i]) { j } }", "for(i in range(0,10,2)){for(j in [i]){j;}}"),
+        ];
+        for (input, expected) in tests.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().expect("Failed to parse input!");
+            assert_eq!( program.statements.len(), 1, "input: {}", input );
+            assert_eq!( &format!("{}", program.statements[0]), expected );
+        }
+
+        let errors = [
+            ("for x in xs { x }", Token::LParen, Token::Ident("x".to_owned())),
+            ("for (1 in xs) { x }", Token::Ident("".to_owned()), Token::Integer(1)),
+            ("for (x of xs) { x }", Token::In, Token::Ident("of".to_owned())),
+            ("for (x in xs { x }", Token::RParen, Token::LBrace),
+        ];
+        for (input, expected_token, got_token) in errors.iter() {
+            match Parser::new(Lexer::new(input)).parse_program() {
+                Err(MonkeyError::UnexpectedToken{expected, got, ..}) => {
+                    assert_eq!( (&expected, &got), (expected_token, got_token), "input: {}", input );
+                },
+                result => panic!("expected UnexpectedToken for {}, but got {:?}", input, result),
+            }
+        }
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "let x = 1; // one\n/* let y = 2; */\nx /* times */ * 2;";
+        let expected = Parser::new(Lexer::new("let x = 1; x * 2;")).parse_program().unwrap();
+        for lexer in [Lexer::new(input), Lexer::new(input).with_trivia()].iter() {
+            let program = Parser::new(lexer.clone()).parse_program().unwrap();
+            assert_eq!( format!("{}", program), format!("{}", expected) );
+        }
+
+        use crate::error::{MonkeyError, LexError};
+        match Parser::new(Lexer::new("let x = 1; /* never closed")).parse_program() {
+            Err(MonkeyError::Lexical(LexError::UnterminatedComment, span)) => assert_eq!( span.start.column, 12 ),
+            result => panic!("expected UnterminatedComment, but got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_lexical_error() {
+        use crate::error::{MonkeyError, LexError};
+
+        for input in ["let x = 1e999;", "1 + 1e999", "let 1e999 = 1;"].iter() {
+            match Parser::new(Lexer::new(input)).parse_program() {
+                Err(MonkeyError::Lexical(LexError::FloatOverflow(literal), _)) => assert_eq!( literal, "1e999" ),
+                result => panic!("expected Lexical error for {}, but got {:?}", input, result),
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        let input = r#"
+let x = 5;
+let = 10;
+let f = fn(a) {
+    return a
+    a + 1;
+};
+let y = add(x, ;
+let z = x * 2;
+"#;
+
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let (program, errors) = p.parse_program_with_recovery();
+
+        let lines: Vec<_> = errors.iter().map(|e| e.span().start.line).collect();
+        assert_eq!( lines, vec![3, 6, 8] );
+
+        let idents: Vec<_> = program.statements.iter().filter_map(|stmt| match &stmt.kind {
+            ast::StatementKind::Let{ident, ..} => Some(format!("{}", ident)),
+            _ => None,
+        }).collect();
+        assert_eq!( idents, vec!["x", "f", "z"] );
+    }
+
+    #[test]
+    fn test_error_recovery_at_closing_brace() {
+        // エラーが囲んでいるブロックの `}` で起きても, その `}` でブロックを閉じる
+        let mut p = Parser::new(Lexer::new("let f = fn(a) { a + }; let g = 1;"));
+        let (program, errors) = p.parse_program_with_recovery();
+
+        assert_eq!( errors.len(), 1, "{:?}", errors );
+        assert_eq!( (errors[0].span().start.line, errors[0].span().start.column), (1, 21) );
+        assert_eq!( program.statements.len(), 2 );
+    }
+
+    #[test]
+    fn test_error_recovery_without_errors() {
+        let input = "let x = 5; if (x) { x; } else { 1; }";
+
+        let mut p1 = Parser::new(Lexer::new(input));
+        let mut p2 = Parser::new(Lexer::new(input));
+        let program = p1.parse_program().expect("Failed to parse");
+        let (recovered, errors) = p2.parse_program_with_recovery();
+
+        assert!( errors.is_empty() );
+        assert_eq!( format!("{}", program), format!("{}", recovered) );
+    }
+}
@@ -1,14 +1,41 @@
 use std::io::{self, BufRead};
 use crate::{
+    Backend,
     lexer::Lexer,
     parser::Parser,
+    ast::Program,
+    object::Object,
     eval::Evaluator,
+    compiler::Compiler,
+    vm::Vm,
+    error::MonkeyError,
+    diagnostics::Diagnostic,
 };
 
 const PROMPT: &str = ">> ";
 
-pub fn start() -> Result<(), io::Error> {
-    let mut env = Evaluator::new();
+/// 入力をまたいで状態を保持する実行系.
+enum Engine {
+    Eval(Evaluator),
+    Vm(Box<Compiler>, Vm),
+}
+impl Engine {
+    fn run(&mut self, program: &Program) -> Result<Object, MonkeyError> {
+        match self {
+            Engine::Eval(env) => env.eval(program),
+            Engine::Vm(compiler, vm) => {
+                let bytecode = compiler.compile(program)?;
+                vm.run(&bytecode)
+            },
+        }
+    }
+}
+
+pub fn start(backend: Backend) -> Result<(), io::Error> {
+    let mut engine = match backend {
+        Backend::Eval => Engine::Eval(Evaluator::new()),
+        Backend::Vm   => Engine::Vm(Box::default(), Vm::new()),
+    };
 
     eprint!("{}", PROMPT);
     for line in io::stdin().lock().lines() {
@@ -20,20 +47,20 @@ pub fn start() -> Result<(), io::Error> {
         let ast = match parser.parse_program() {
             Ok(ast) => ast,
             Err(e) => {
-                eprint!("[ERROR] {:?}\n{}", e, PROMPT);
+                eprint!("{}{}", Diagnostic::new(&e, &input), PROMPT);
                 continue;
             },
         };
 
-        match env.eval(&ast) {
+        match engine.run(&ast) {
             Ok(obj) => println!("{}", obj),
             Err(e) => {
-                eprint!("[ERROR] {:?}\n{}", e, PROMPT);
+                eprint!("{}{}", Diagnostic::new(&e, &input), PROMPT);
                 continue;
             },
         }
         eprint!("{}", PROMPT);
     }
-    eprintln!("");
+    eprintln!();
     Ok(())
 }
@@ -0,0 +1,71 @@
+use std::fmt;
+
+/// ソースコード中の位置.
+/// `offset` は先頭からのバイトオフセット, `line` と `column` は 1 始まりの行番号と列番号 (文字単位).
+/// `Default` は行番号 0 の「位置を持たない」ことを表すダミーの位置を返す.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+impl Position {
+    /// ソースコードの先頭を表す位置.
+    pub fn start() -> Self {
+        Position { offset: 0, line: 1, column: 1 }
+    }
+}
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// ソースコード中の範囲. `start` を含み `end` を含まない.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Span { start, end }
+    }
+
+    /// `self` の先頭から `other` の末尾までを覆う範囲を返す.
+    pub fn to(self, other: Span) -> Span {
+        Span { start: self.start, end: other.end }
+    }
+
+    /// ソースコード上の位置を持たない (組み込み関数などが生成した) 範囲かどうか.
+    pub fn is_dummy(&self) -> bool {
+        self.start.line == 0
+    }
+}
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::span::{Position, Span};
+
+    #[test]
+    fn test_span_to() {
+        let a = Span::new(
+            Position { offset: 0, line: 1, column: 1 },
+            Position { offset: 3, line: 1, column: 4 },
+        );
+        let b = Span::new(
+            Position { offset: 6, line: 2, column: 1 },
+            Position { offset: 9, line: 2, column: 4 },
+        );
+        let c = a.to(b);
+        assert_eq!( c.start, a.start );
+        assert_eq!( c.end, b.end );
+        assert!( !c.is_dummy() );
+        assert!( Span::default().is_dummy() );
+    }
+}
@@ -1,34 +1,67 @@
-use crate::operator::Precedence;
+use std::fmt;
+use crate::{operator::{Precedence, Infix}, span::Span, error::LexError, bigint::BigInt};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Illegal,
+    /// 字句解析のエラー. 構文解析器が MonkeyError::Lexical として報告する.
+    Error(LexError),
+    /// コメント. crate::lexer::Lexer::with_trivia を指定したときだけ出力される. 区切り記号を含む.
+    Comment(String),
     EOF,
     Assign,
+    /// 複合代入演算子 `+=`, `-=`, `*=`, `/=`
+    PlusAssign,
+    MinusAssign,
+    AsteriskAssign,
+    SlashAssign,
     Plus,
     Minus,
     Bang,
     Asterisk,
     Slash,
+    Percent,
+    Power,
     LT,
     GT,
+    LTEq,
+    GTEq,
     Eq,
     NotEq,
+    And,
+    Or,
+    Ampersand,
+    Pipe,
+    Caret,
+    Shl,
+    Shr,
     Comma,
+    Colon,
     Semicolon,
     LParen,
     RParen,
     LBrace,
     RBrace,
+    LBracket,
+    RBracket,
     Function,
     Let,
+    Const,
     True,
     False,
     If,
     Else,
     Return,
+    While,
+    For,
+    In,
+    Break,
+    Continue,
     Ident(String),
-    Integer(i32),
+    Integer(i64),
+    /// i64 に収まらない整数リテラル. トークンを小さく保つために Box に入れる.
+    BigInteger(Box<BigInt>),
+    Float(f64),
     String(String),
 }
 
@@ -39,28 +72,127 @@ impl Token {
         match ident {
             "fn"     => Some(Token::Function),
             "let"    => Some(Token::Let     ),
+            "const"  => Some(Token::Const   ),
             "true"   => Some(Token::True    ),
             "false"  => Some(Token::False   ),
             "if"     => Some(Token::If      ),
             "else"   => Some(Token::Else    ),
             "return" => Some(Token::Return  ),
+            "while"  => Some(Token::While   ),
+            "for"    => Some(Token::For     ),
+            "in"     => Some(Token::In      ),
+            "break"  => Some(Token::Break   ),
+            "continue" => Some(Token::Continue),
             _        => None,
         }
     }
 
+    /// 複合代入演算子であれば, 対応する中置演算子を返す.
+    pub fn compound_operator(&self) -> Option<Infix> {
+        match self {
+            Token::PlusAssign     => Some(Infix::Plus),
+            Token::MinusAssign    => Some(Infix::Minus),
+            Token::AsteriskAssign => Some(Infix::Asterisk),
+            Token::SlashAssign    => Some(Infix::Slash),
+            _                     => None,
+        }
+    }
+
     /// 中置演算子の優先順位を定義する. crate::operator::Precedence 参照.
     pub fn precedence(&self) -> Precedence {
         match self {
+            Token::Or       => Precedence::LogicalOr,
+            Token::And      => Precedence::LogicalAnd,
             Token::Eq       => Precedence::Equals,
             Token::NotEq    => Precedence::Equals,
             Token::LT       => Precedence::LessGreater,
             Token::GT       => Precedence::LessGreater,
+            Token::LTEq     => Precedence::LessGreater,
+            Token::GTEq     => Precedence::LessGreater,
+            Token::Pipe     => Precedence::BitOr,
+            Token::Caret    => Precedence::BitXor,
+            Token::Ampersand => Precedence::BitAnd,
+            Token::Shl      => Precedence::Shift,
+            Token::Shr      => Precedence::Shift,
             Token::Plus     => Precedence::Sum,
             Token::Minus    => Precedence::Sum,
             Token::Slash    => Precedence::Product,
             Token::Asterisk => Precedence::Product,
+            Token::Percent  => Precedence::Product,
+            Token::Power    => Precedence::Power,
             Token::LParen   => Precedence::Call,
+            Token::LBracket => Precedence::Index,
             _                => Precedence::Lowest,
         }
     }
 }
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Illegal    => write!(f, "illegal token"),
+            Token::Error(_)   => write!(f, "invalid token"),
+            Token::Comment(_) => write!(f, "comment"),
+            Token::EOF        => write!(f, "end of input"),
+            Token::Assign     => write!(f, "`=`"),
+            Token::PlusAssign => write!(f, "`+=`"),
+            Token::MinusAssign => write!(f, "`-=`"),
+            Token::AsteriskAssign => write!(f, "`*=`"),
+            Token::SlashAssign => write!(f, "`/=`"),
+            Token::Plus       => write!(f, "`+`"),
+            Token::Minus      => write!(f, "`-`"),
+            Token::Bang       => write!(f, "`!`"),
+            Token::Asterisk   => write!(f, "`*`"),
+            Token::Slash      => write!(f, "`/`"),
+            Token::Percent    => write!(f, "`%`"),
+            Token::Power      => write!(f, "`**`"),
+            Token::LT         => write!(f, "`<`"),
+            Token::GT         => write!(f, "`>`"),
+            Token::LTEq       => write!(f, "`<=`"),
+            Token::GTEq       => write!(f, "`>=`"),
+            Token::Eq         => write!(f, "`==`"),
+            Token::NotEq      => write!(f, "`!=`"),
+            Token::And        => write!(f, "`&&`"),
+            Token::Or         => write!(f, "`||`"),
+            Token::Ampersand  => write!(f, "`&`"),
+            Token::Pipe       => write!(f, "`|`"),
+            Token::Caret      => write!(f, "`^`"),
+            Token::Shl        => write!(f, "`<<`"),
+            Token::Shr        => write!(f, "`>>`"),
+            Token::Comma      => write!(f, "`,`"),
+            Token::Colon      => write!(f, "`:`"),
+            Token::Semicolon  => write!(f, "`;`"),
+            Token::LParen     => write!(f, "`(`"),
+            Token::RParen     => write!(f, "`)`"),
+            Token::LBrace     => write!(f, "`{{`"),
+            Token::RBrace     => write!(f, "`}}`"),
+            Token::LBracket   => write!(f, "`[`"),
+            Token::RBracket   => write!(f, "`]`"),
+            Token::Function   => write!(f, "`fn`"),
+            Token::Let        => write!(f, "`let`"),
+            Token::Const      => write!(f, "`const`"),
+            Token::True       => write!(f, "`true`"),
+            Token::False      => write!(f, "`false`"),
+            Token::If         => write!(f, "`if`"),
+            Token::Else       => write!(f, "`else`"),
+            Token::Return     => write!(f, "`return`"),
+            Token::While      => write!(f, "`while`"),
+            Token::For        => write!(f, "`for`"),
+            Token::In         => write!(f, "`in`"),
+            Token::Break      => write!(f, "`break`"),
+            Token::Continue   => write!(f, "`continue`"),
+            Token::Ident(s) if s.is_empty() => write!(f, "identifier"),
+            Token::Ident(s)   => write!(f, "identifier `{}`", s),
+            Token::Integer(n) => write!(f, "integer `{}`", n),
+            Token::BigInteger(n) => write!(f, "integer `{}`", n),
+            Token::Float(x)   => write!(f, "float `{:?}`", x),
+            Token::String(s)  => write!(f, "string \"{}\"", s),
+        }
+    }
+}
+
+/// ソースコード上の位置を伴うトークン. crate::lexer::Lexer::next_token が生成する.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
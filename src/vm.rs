@@ -0,0 +1,359 @@
+use std::{rc::Rc, cell::RefCell};
+use crate::{
+    code::{self, Opcode, PREFIX_OPERATORS, INFIX_OPERATORS},
+    compiler::{Bytecode, CompiledFunction, Constant, Capture},
+    object::{Object, HashKey, HashTable},
+    error::MonkeyError,
+    span::Span,
+    eval,
+    builtins,
+};
+
+/// 変数に束縛された値. `const` で束縛された値は書き換えられない.
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub value: Object,
+    pub mutable: bool,
+}
+
+/// 変数を格納するセル. クロージャはセルを共有することで変数を参照で捕捉する.
+/// 値が束縛される前は `None`.
+pub type Cell = Rc<RefCell<Option<Binding>>>;
+
+/// 実行時のクロージャ. コンパイル済みの関数と捕捉した変数のセルの組.
+#[derive(Debug)]
+pub struct Closure {
+    pub function: Rc<CompiledFunction>,
+    pub free: Vec<Cell>,
+}
+
+/// 関数呼び出し一つ分の状態.
+#[derive(Debug)]
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    /// 呼び出し時のスタックの高さ. 戻るときにここまでスタックを縮める.
+    base: usize,
+    locals: Vec<Cell>,
+}
+
+/// バイトコードを実行するスタック仮想機械.
+/// グローバル変数は複数回の run の間で引き継がれる (REPL 用).
+#[derive(Debug)]
+pub struct Vm {
+    globals: Vec<Option<Binding>>,
+    stack: Vec<Object>,
+}
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Vm {
+    /// 組み込み関数をグローバル変数に束縛した Vm を生成する.
+    /// 組み込み関数の順序は crate::compiler::Compiler::new が割り当てる番号と一致する.
+    pub fn new() -> Self {
+        Vm {
+            globals: builtins::all().map(|builtin| Some(Binding { value: Object::Builtin(builtin), mutable: true })).collect(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// バイトコードを実行し, プログラムの値を返す.
+    pub fn run(&mut self, bytecode: &Bytecode) -> Result<Object, MonkeyError> {
+        if self.globals.len() < bytecode.global_names.len() {
+            self.globals.resize(bytecode.global_names.len(), None);
+        }
+        self.stack.clear();
+
+        let main = Closure { function: Rc::clone(&bytecode.main), free: Vec::new() };
+        // トップレベルの局所変数はブロックの中で束縛したものだけ
+        let locals = bytecode.main.local_names.iter().map(|_| Rc::new(RefCell::new(None))).collect();
+        let mut frame = Frame { closure: Rc::new(main), ip: 0, base: 0, locals };
+        let mut callers: Vec<Frame> = Vec::new();
+
+        loop {
+            let function = Rc::clone(&frame.closure.function);
+            let ins = &function.instructions;
+
+            // 関数は必ず ReturnValue で終わるので, 命令列の終わりに達するのはトップレベルだけ
+            if frame.ip >= ins.len() {
+                return Ok(self.stack.pop().unwrap_or(Object::Null));
+            }
+
+            let ip = frame.ip;
+            let op = Opcode::from_byte(ins[ip]).unwrap();
+            frame.ip += 1 + op.operand_widths().iter().sum::<usize>();
+
+            match op {
+                Opcode::Constant => {
+                    match &bytecode.constants[code::read_u16(ins, ip + 1)] {
+                        Constant::Object(obj) => self.stack.push(obj.clone()),
+                        Constant::Function(_) => unreachable!(),
+                    }
+                },
+                Opcode::Pop   => { self.pop(); },
+                Opcode::True  => self.stack.push(Object::Bool(true)),
+                Opcode::False => self.stack.push(Object::Bool(false)),
+                Opcode::Null  => self.stack.push(Object::Null),
+                Opcode::Prefix => {
+                    let op = &PREFIX_OPERATORS[code::read_u8(ins, ip + 1)];
+                    let right = self.pop();
+                    let result = eval::eval_prefix_expression(op, right, function.span_at(ip))?;
+                    self.stack.push(result);
+                },
+                Opcode::Infix => {
+                    let op = &INFIX_OPERATORS[code::read_u8(ins, ip + 1)];
+                    let right = self.pop();
+                    let left = self.pop();
+                    let result = eval::eval_infix_expression(op, left, right, function.span_at(ip))?;
+                    self.stack.push(result);
+                },
+                Opcode::Jump => {
+                    frame.ip = code::read_u16(ins, ip + 1);
+                },
+                Opcode::JumpNotTruthy => {
+                    if !self.pop().is_truthy() {
+                        frame.ip = code::read_u16(ins, ip + 1);
+                    }
+                },
+                Opcode::JumpIfFalsy | Opcode::JumpIfTruthy => {
+                    let truthy = self.stack.last().is_some_and(Object::is_truthy);
+                    if truthy == (op == Opcode::JumpIfTruthy) {
+                        frame.ip = code::read_u16(ins, ip + 1);
+                    } else {
+                        self.pop();
+                    }
+                },
+                Opcode::GetGlobal | Opcode::LoadGlobal => {
+                    let index = code::read_u16(ins, ip + 1);
+                    match &self.globals[index] {
+                        Some(binding) => self.stack.push(binding.value.clone()),
+                        None => return Err(unbound(op, &bytecode.global_names[index], function.span_at(ip))),
+                    }
+                },
+                Opcode::SetGlobal | Opcode::SetConstGlobal => {
+                    let index = code::read_u16(ins, ip + 1);
+                    self.globals[index] = Some(Binding { value: self.pop(), mutable: op == Opcode::SetGlobal });
+                },
+                Opcode::GetLocal | Opcode::LoadLocal => {
+                    let index = code::read_u8(ins, ip + 1);
+                    let value = frame.locals[index].borrow().as_ref().map(|binding| binding.value.clone());
+                    match value {
+                        Some(obj) => self.stack.push(obj),
+                        None => return Err(unbound(op, &function.local_names[index], function.span_at(ip))),
+                    }
+                },
+                Opcode::SetLocal | Opcode::SetConstLocal => {
+                    let index = code::read_u8(ins, ip + 1);
+                    *frame.locals[index].borrow_mut() = Some(Binding { value: self.pop(), mutable: op == Opcode::SetLocal });
+                },
+                Opcode::GetFree | Opcode::LoadFree => {
+                    let index = code::read_u8(ins, ip + 1);
+                    let value = frame.closure.free[index].borrow().as_ref().map(|binding| binding.value.clone());
+                    match value {
+                        Some(obj) => self.stack.push(obj),
+                        None => return Err(unbound(op, &function.free_names[index], function.span_at(ip))),
+                    }
+                },
+                Opcode::AssignGlobal => {
+                    let index = code::read_u16(ins, ip + 1);
+                    assignable(self.globals[index].as_ref(), &bytecode.global_names[index], function.span_at(ip))?;
+                    self.globals[index] = Some(Binding { value: self.pop(), mutable: true });
+                },
+                Opcode::AssignLocal | Opcode::AssignFree => {
+                    let index = code::read_u8(ins, ip + 1);
+                    let (cell, name) = match op {
+                        Opcode::AssignLocal => (&frame.locals[index], &function.local_names[index]),
+                        _                   => (&frame.closure.free[index], &function.free_names[index]),
+                    };
+                    assignable(cell.borrow().as_ref(), name, function.span_at(ip))?;
+                    *cell.borrow_mut() = Some(Binding { value: self.pop(), mutable: true });
+                },
+                Opcode::ResetLocals => {
+                    let first = code::read_u8(ins, ip + 1);
+                    let count = code::read_u8(ins, ip + 2);
+                    for cell in frame.locals[first..first + count].iter_mut() {
+                        *cell = Rc::new(RefCell::new(None));
+                    }
+                },
+                Opcode::Array => {
+                    let len = code::read_u16(ins, ip + 1);
+                    let elements = self.stack.split_off(self.stack.len() - len);
+                    self.stack.push(Object::Array(elements));
+                },
+                Opcode::Hash => {
+                    let len = code::read_u16(ins, ip + 1);
+                    let elements = self.stack.split_off(self.stack.len() - 2 * len);
+                    let mut table = HashTable::new();
+                    for (pair, span) in elements.chunks(2).zip(function.spans_at(ip)) {
+                        let key = HashKey::from_object(&pair[0])
+                            .map_err(|t| MonkeyError::UnhashableKey(t, span))?;
+                        table.insert(key, pair[1].clone());
+                    }
+                    self.stack.push(Object::Hash(table));
+                },
+                Opcode::Index => {
+                    let index = self.pop();
+                    let left = self.pop();
+                    let result = eval::eval_index_expression(left, index, function.span_at(ip))?;
+                    self.stack.push(result);
+                },
+                Opcode::Call => {
+                    let argc = code::read_u8(ins, ip + 1);
+                    let span = function.span_at(ip);
+                    let args = self.stack.split_off(self.stack.len() - argc);
+                    match self.pop() {
+                        Object::Closure(closure) => {
+                            let expected = closure.function.num_parameters;
+                            if argc != expected {
+                                return Err(MonkeyError::IncorrectNumberOfArgs{ expected, got: argc, span });
+                            }
+                            let mut locals: Vec<Cell> = args.into_iter()
+                                .map(|arg| Rc::new(RefCell::new(Some(Binding { value: arg, mutable: true }))))
+                                .collect();
+                            locals.resize_with(closure.function.local_names.len(), || Rc::new(RefCell::new(None)));
+
+                            let callee = Frame { closure, ip: 0, base: self.stack.len(), locals };
+                            callers.push(std::mem::replace(&mut frame, callee));
+                        },
+                        Object::Builtin(builtin) => {
+                            let result = builtin.call(&args).map_err(|e| e.with_span(span))?;
+                            self.stack.push(result);
+                        },
+                        obj => return Err(MonkeyError::NotCallable(obj.type_of(), span)),
+                    }
+                },
+                Opcode::ReturnValue => {
+                    let value = self.pop();
+                    match callers.pop() {
+                        Some(caller) => {
+                            self.stack.truncate(frame.base);
+                            frame = caller;
+                            self.stack.push(value);
+                        },
+                        None => return Ok(value),
+                    }
+                },
+                Opcode::Closure => {
+                    let function = match &bytecode.constants[code::read_u16(ins, ip + 1)] {
+                        Constant::Function(function) => Rc::clone(function),
+                        Constant::Object(_) => unreachable!(),
+                    };
+                    let free = function.captures.iter().map(|capture| match capture {
+                        Capture::Local(index) => Rc::clone(&frame.locals[*index]),
+                        Capture::Free(index)  => Rc::clone(&frame.closure.free[*index]),
+                    }).collect();
+                    self.stack.push(Object::Closure(Rc::new(Closure { function, free })));
+                },
+                Opcode::Iterate => {
+                    let items = eval::iterate(self.pop(), function.span_at(ip))?;
+                    self.stack.push(Object::Array(items));
+                    self.stack.push(Object::Integer(0));
+                },
+                Opcode::Next => {
+                    let len = self.stack.len();
+                    let (item, index) = match (&self.stack[len - 2], &self.stack[len - 1]) {
+                        (Object::Array(items), Object::Integer(index)) => (items.get(*index as usize).cloned(), *index),
+                        _ => unreachable!(),
+                    };
+                    match item {
+                        Some(item) => {
+                            self.stack[len - 1] = Object::Integer(index + 1);
+                            self.stack.push(item);
+                        },
+                        None => {
+                            self.stack.truncate(len - 2);
+                            frame.ip = code::read_u16(ins, ip + 1);
+                        },
+                    }
+                },
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Object {
+        self.stack.pop().unwrap_or(Object::Null)
+    }
+}
+
+/// 変数 `name` の束縛 `binding` に代入できるかどうかを確かめる.
+/// `const` の束縛への代入のうち, コンパイル時に分からなかったものはここで AssignToConstant になる.
+fn assignable(binding: Option<&Binding>, name: &str, span: Span) -> Result<(), MonkeyError> {
+    match binding {
+        None => Err(MonkeyError::AssignToUndeclared(name.to_owned(), span)),
+        Some(binding) if !binding.mutable => Err(MonkeyError::AssignToConstant(name.to_owned(), span)),
+        Some(_) => Ok(()),
+    }
+}
+
+/// 束縛されていない変数 `name` を読もうとしたときのエラー. 複合代入の代入先なら AssignToUndeclared になる.
+fn unbound(op: Opcode, name: &str, span: Span) -> MonkeyError {
+    match op {
+        Opcode::LoadGlobal | Opcode::LoadLocal | Opcode::LoadFree => MonkeyError::AssignToUndeclared(name.to_owned(), span),
+        _ => MonkeyError::IdentifierNotFound(name.to_owned(), span),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lexer::Lexer,
+        parser::Parser,
+        compiler::Compiler,
+        vm::Vm,
+        object::Object,
+        error::MonkeyError,
+    };
+
+    fn run(input: &str) -> Result<Object, MonkeyError> {
+        let program = Parser::new(Lexer::new(input)).parse_program()?;
+        let bytecode = Compiler::new().compile(&program)?;
+        Vm::new().run(&bytecode)
+    }
+
+    #[test]
+    fn run_expression() {
+        let tests = [
+            ("1 + 2 * 3", Object::Integer(7)),
+            ("if (1 > 2) { 1 } else { 2 }", Object::Integer(2)),
+            ("if (false) { 1 }", Object::Null),
+            ("let x = 3; [x, x * x][1]", Object::Integer(9)),
+            ("let f = fn(a) { fn(b) { a + b } }; f(1)(2)", Object::Integer(3)),
+            ("let f = fn(n) { if (n == 0) { return 0; } n + f(n - 1) }; f(10)", Object::Integer(55)),
+        ];
+        for (input, expected) in tests.iter() {
+            assert_eq!( &run(input).unwrap(), expected, "input: {}", input );
+        }
+    }
+
+    #[test]
+    fn closures_share_variables() {
+        // 同じ関数呼び出しで作られたクロージャは局所変数のセルを共有する
+        let input = "let f = fn() { let g = fn() { x }; let x = 5; g() }; f()";
+        assert_eq!( run(input).unwrap(), Object::Integer(5) );
+    }
+
+    #[test]
+    fn globals_persist_across_runs() {
+        let mut compiler = Compiler::new();
+        let mut vm = Vm::new();
+        for (input, expected) in [("let a = 2;", Object::Null), ("a * 21", Object::Integer(42))].iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            let bytecode = compiler.compile(&program).unwrap();
+            assert_eq!( &vm.run(&bytecode).unwrap(), expected );
+        }
+    }
+
+    #[test]
+    fn run_errors() {
+        match run("let f = fn(x) { x }; f()") {
+            Err(MonkeyError::IncorrectNumberOfArgs{ expected: 1, got: 0, .. }) => {},
+            result => panic!("expected IncorrectNumberOfArgs, but got {:?}", result),
+        }
+        match run("let f = fn() { y }; f()") {
+            Err(MonkeyError::IdentifierNotFound(name, _)) => assert_eq!( name, "y" ),
+            result => panic!("expected IdentifierNotFound, but got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use monkey_interpreter::{
+    evaluate_with,
+    Backend,
+};
+
+/// 評価器と仮想機械で同じ結果 (エラーの場合は種類と位置も含めて) になることを確かめる.
+fn assert_same(input: &str) {
+    let expected = evaluate_with(input, Backend::Eval);
+    let got = evaluate_with(input, Backend::Vm);
+    match (&expected, &got) {
+        (Ok(e), Ok(g)) => assert_eq!( format!("{}", g), format!("{}", e), "input: {}", input ),
+        (Err(e), Err(g)) => {
+            assert_eq!( format!("{}", g), format!("{}", e), "input: {}", input );
+            assert_eq!( g.span(), e.span(), "input: {}", input );
+        },
+        _ => panic!("backends disagree on {:?}: eval = {:?}, vm = {:?}", input, expected, got),
+    }
+}
+
+#[test]
+fn same_results() {
+    let inputs = [
+        "5", "-5", "!true", "!!5", "1 + 2 * 3 - 4 / 2", "(1 < 2) == true", "1 != 2",
+        r#""monkey""#, "if (1 > 2) { 10 }", "if (1 < 2) { 10 } else { 20 }", "if (false) { 1 }",
+        "let a = 5; let b = a * 2; a + b", "let a = 1; let a = a + 1; a",
+        "return 10; 9", "if (true) { if (true) { return 1; } return 2; }",
+        "[1, 2 * 2, 3][1]", "[1, 2][5]", r#"{"a": 1, true: 2, 3: 3}[true]"#, r#"{"a": 1}["b"]"#,
+        "len([1, 2, 3])", r#"len("four")"#, "first(rest(push([1], 2)))", "last([])",
+        "let f = fn(x, y) { x + y }; f(1, 2)", "fn() { }()", "fn(x) { let y = x; y * 2 }(4)",
+        "let add = fn(a) { fn(b) { a + b } }; add(2)(3)",
+        "let f = fn(a) { fn(b) { fn(c) { a + b + c } } }; f(1)(2)(3)",
+        "let counter = fn(n) { if (n == 0) { 0 } else { 1 + counter(n - 1) } }; counter(20)",
+        "let f = fn() { let a = 1; let g = fn() { a }; let a = 2; g() }; f()",
+        "let map = fn(xs, f) { if (len(xs) == 0) { [] } else { push(map(rest(xs), f), f(first(xs))) } }; map([1, 2, 3], fn(x) { x * x })",
+        "let len = fn(x) { 0 }; len([1])",
+        "1.5 * 2", "7 / 2", "7 / 2.0", "1 / 0.0", "0.0 / 0.0 == 0.0 / 0.0", "[1 < 1.5, -2.5, 1e-9]",
+        r#""mon" + "key""#, r#""ab" * 3"#, r#"["a" < "b", "b" > "a", "a" == "a", "a" != "a"]"#,
+        "let even = fn(n) { if (n == 0) { true } else { odd(n - 1) } }; let odd = fn(n) { if (n == 0) { false } else { even(n - 1) } }; odd(9)",
+        "let a = 1; let f = fn() { a }; let a = 2; f()",
+        "2147483647 + 1", "-(-9223372036854775807 - 1)", "let f = fn(x) { x * x }; f(4294967296)",
+        "99999999999999999999 / 3 - 33333333333333333333", r#"{18446744073709551616: 1}[2 * 9223372036854775808]"#,
+        "[7 % 3, -7 % 3, 7.5 % 2, 2 ** 3 ** 2, -2 ** 2, 2 ** -1, 2 ** 70, 3 <= 3, 2 >= 3]",
+        "[6 & 3, 6 | 3, 6 ^ 3, 1 << 70, -7 >> 1, (1 << 70) >> 69]", "(2 ** 30000) * (2 ** 30000) == 2 ** 60000",
+        r#"[true && 1, false && 1, 0 || 1, false || "x", false && undefined, true || undefined]"#,
+        "let f = fn(n) { n > 0 && f(n - 1) || n == 0 }; [f(10), f(-1)]",
+        "let i = 0; while (i < 1000) { i += 1; } i", "while (false) { 1 }",
+        "let i = 0; let s = 0; while (true) { i += 1; if (i > 10) { break; } if (i % 3 == 0) { continue; } s += i; } s",
+        "let f = fn(n) { let i = 0; while (true) { if (i == n) { return i * 10; } i += 1; } }; f(5)",
+        "let f = fn() { let i = 0; let xs = []; while (i < 5) { i += 1; xs = push(xs, i); } xs }; f()",
+        "let s = 0; for (x in [1, 2, 3]) { s += x; } s", "for (x in []) { 1 }",
+        r#"let f = fn(s) { let out = ""; for (c in s) { out = c + out; } out }; f("añb")"#,
+        r#"let ks = []; for (k in {"b": 1, 2: 2, true: 3}) { ks = push(ks, k); } ks"#,
+        "let f = fn() { let s = 0; for (i in range(20)) { if (i > 9) { break; } if (i % 3 == 0) { continue; } s += i; } s }; f()",
+        "let f = fn(xs) { for (x in xs) { for (y in xs) { if (x * y == 6) { return [x, y]; } } } }; f(range(1, 5))",
+        "let n = 0; for (i in range(10, 0, -3)) { for (j in range(i)) { if (j == 2) { break; } n += 1; } } n",
+        "let x = 0; for (x in [4, 5]) { 1 + if (x == 5) { break; } } x",
+        "let fs = []; for (i in range(3)) { fs = push(fs, fn() { i }); } fs[0]()",
+        "let x = 10; x += 5; x -= 3; x *= 2; x /= 4; x", "let x = 1; x = 2",
+        "let x = 1; let f = fn() { x = 2; }; f(); x", "let i = 0; while (i < 3) { i = i + 1 } i", "let i = 0; if (true) { i += 1 } i",
+        "let counter = fn() { let n = 0; [fn() { n += 1; n }, fn() { n }] }; let c = counter(); c[0](); c[0](); c[1]()",
+        "let f = fn() { let total = 0; for (x in range(1, 5)) { total += x; } total }; f()",
+        "let f = fn(n) { let a = 0; let b = 1; while (n > 0) { let t = a + b; a = b; b = t; n -= 1; } a }; f(90)",
+        "let x = 1; if (true) { let x = 2; } x", "let x = 1; if (true) { let x = 2; x }", "let x = 1; if (true) { x = 2; } x",
+        "let f = fn(x) { if (x > 0) { let x = x * 2; x } else { let y = 0; y } }; [f(5), f(-1)]",
+        "let x = 1; if (true) { let y = x + 1; if (true) { let x = y * 10; x } }",
+        "let fs = []; let i = 0; while (i < 3) { let j = i; fs = push(fs, fn() { j }); i += 1; } [fs[0](), fs[2]()]",
+        "let fs = []; for (i in range(3)) { fs = push(fs, fn() { i }); } [fs[0](), fs[2]()]",
+        "let f = fn() { let fs = []; for (i in range(3)) { let g = fn() { i * h() }; let h = fn() { 10 }; fs = push(fs, g); } fs[1]() }; f()",
+        "if (true) { let a = 1; let f = fn() { a }; a = 5; f() }",
+        "let x = 1; { let x = 2; } x", "let x = 1; { let x = 2; { let x = x * 10; x } }", "let x = 1; { x = 2; }; x",
+        "let fs = []; { let a = 1; fs = push(fs, fn() { a }); } { let a = 2; fs = push(fs, fn() { a }); } [fs[0](), fs[1]()]",
+        "let x = 1; if (true) { let x = x + 1; let x = x * 10; x }", "let x = 1; if (true) { x = 5; let x = 2; } x",
+        "let i = 0; let n = 0; while (i < 3) { i += 1; let x = if (true) { break; }; n += 1; } [i, n]",
+        "let xs = []; for (i in range(3)) { xs = push(xs, [1, if (i == 1) { continue; }]); } xs",
+        "let s = 0; for (i in range(3)) { s += 1 + if (i == 1) { continue; } else { i }; } s",
+        "let n = 0; for (x in [1, 2]) { for (y in if (x == 1) { continue; } else { [x] }) { n += y; } } n",
+        "let n = 0; while (true) { while (if (true) { break; }) { } n = 1; break; } n",
+        "let f = fn() { let x = if (true) { return 5; }; 10 }; f()", "let f = fn() { 1 + if (true) { return 3; } }; f()",
+        "let x = if (true) { return 7; }; 10", "let f = fn(n) { [n, if (n > 0) { return -n; }] }; [f(0), f(2)]",
+        "const x = 1; if (true) { const x = 2; x }", "let x = 1; const x = 2; x",
+        "let s = 0; for (i in range(3)) { const j = i * 2; s += j; } s",
+        "let x = 1; if (true) { const x = 2 } x", "let f = fn() { let a = 3 }; [f(), 4]",
+    ];
+    for input in inputs.iter() {
+        assert_same(input);
+    }
+}
+
+#[test]
+fn same_errors() {
+    let inputs = [
+        "foobar", "1 + true", "true + false", "-true", "fn(x) { x }(1, 2)", "len(1, 2)", "len(1)",
+        r#"{[1]: 2}"#, "[1][true]", "1[0]", "let f = fn() { undefined }; f()",
+        "let f = fn() { g() }; f()", "5()", "let x = [1]; x(1)",
+        "1.5 + true", "{1.5: 1}", r#""a" + 1"#, r#""a" - "b""#, "-true", "-\"a\"", "[1] + [2]", "\"a\" == 1", "if (false) { 1 } == if (false) { 1 }",
+        "5 / 0", "5 % 0", "1 << -1", "2 ** 100000", "let x = 3; while (true) { x = x * x; }", "let f = fn(x) { f(x * x) }; f(7)", "1.5 | 1", "true && undefined", "false || 1 + true",
+        "99999999999999999999 / 0", "[1][9223372036854775808]", r#""a" * 99999999999999999999"#,
+        "for (x in 5) { x }", "let f = fn() { for (x in fn() { 1 }) { x } }; f()", "range(0, 1, 0)", "range(1.5)", "range()", "range(1, 2, 3, 4)",
+        "y = 1;", "let f = fn() { z = 1; }; f()", "x += 1;", "let f = fn() { z *= 2; }; f()", "let x = 1; x += true;", "let f = fn() { let g = fn() { w = 1; }; g() }; f()",
+        "if (true) { let y = 1; } y", "{ let z = 1; } z", "let f = fn() { { let w = 1; } w }; f()", "while (true) { let z = 1; break; } z", "for (x in [1]) { x } x",
+        "let f = fn() { if (true) { let w = 1; } w }; f()",
+        "const x = 1; x = 2;", "const x = 1; x += 2;", "const x = 1; let x = 2;", "const x = 1; const x = 2;",
+        "const x = 1; let f = fn() { x = 2; }; f()", "let f = fn() { const y = 1; let g = fn() { y = 2; }; g() }; f()",
+        // 代入が `const` より前にコンパイルされる
+        "let f = fn() { x = 2; }; const x = 1; f(); x", "let g = fn() { let h = fn() { x = 5; }; const x = 1; h(); x }; g()",
+        "let f = fn() { x += 2; }; const x = 1; f(); x",
+    ];
+    for input in inputs.iter() {
+        assert_same(input);
+    }
+}
@@ -0,0 +1,152 @@
+use std::{fmt, error::Error, rc::Rc, cell::RefCell, collections::HashMap};
+use monkey_interpreter::{
+    lexer::Lexer,
+    parser::Parser,
+    eval::{Evaluator, call_function},
+    object::Object,
+    builtins::Arity,
+    convert::{IntoMonkey, FromMonkey},
+    error::MonkeyError,
+};
+
+fn run(env: &mut Evaluator, input: &str) -> Result<Object, MonkeyError> {
+    let program = Parser::new(Lexer::new(input)).parse_program()?;
+    env.eval(&program)
+}
+
+#[derive(Debug, PartialEq)]
+struct UserNotFound(i64);
+impl fmt::Display for UserNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "user {} not found", self.0)
+    }
+}
+impl Error for UserNotFound {}
+
+fn fetch_user(args: &[Object]) -> Result<Object, MonkeyError> {
+    match args[0] {
+        Object::Integer(1) => Ok(Object::String("alice".to_owned())),
+        Object::Integer(id) => Err(MonkeyError::host(UserNotFound(id))),
+        ref arg => Err(MonkeyError::InvalidArgument{
+            function: "fetch_user".to_owned(), got: arg.type_of(), span: Default::default(),
+        }),
+    }
+}
+
+#[test]
+fn register_host_function() -> Result<(), MonkeyError> {
+    let mut env = Evaluator::new();
+    env.register_fn_with_arity("fetch_user", Arity::Exact(1), fetch_user);
+
+    assert_eq!( run(&mut env, "fetch_user(1)")?, Object::String("alice".to_owned()) );
+    assert_eq!( run(&mut env, "let f = fetch_user; f(2 - 1)")?, Object::String("alice".to_owned()) );
+
+    Ok(())
+}
+
+#[test]
+fn host_function_arity() {
+    let mut env = Evaluator::new();
+    env.register_fn_with_arity("fetch_user", Arity::Exact(1), fetch_user);
+
+    match run(&mut env, "fetch_user(1, 2)") {
+        Err(MonkeyError::IncorrectNumberOfArgs{expected, got, span}) => {
+            assert_eq!( (expected, got), (1, 2) );
+            assert_eq!( (span.start.column, span.end.column), (1, 17) );
+        },
+        result => panic!("expected IncorrectNumberOfArgs, but got {:?}", result),
+    }
+}
+
+#[test]
+fn host_function_typed_error() {
+    let mut env = Evaluator::new();
+    env.register_fn_with_arity("fetch_user", Arity::Exact(1), fetch_user);
+
+    let err = run(&mut env, "let id = 42;\nfetch_user(id);").unwrap_err();
+    assert_eq!( err.downcast_host::<UserNotFound>(), Some(&UserNotFound(42)) );
+    assert_eq!( err.span().start.line, 2 );
+    assert_eq!( format!("{}", err), "user 42 not found" );
+    assert!( err.source().is_some() );
+
+    let err = run(&mut env, "fetch_user(true);").unwrap_err();
+    assert!( err.downcast_host::<UserNotFound>().is_none() );
+}
+
+#[test]
+fn host_closure_captures_state() -> Result<(), MonkeyError> {
+    let log = Rc::new(RefCell::new(Vec::new()));
+
+    let mut env = Evaluator::new();
+    let sink = Rc::clone(&log);
+    env.register_fn("log", move |args: &[Object]| {
+        sink.borrow_mut().extend(args.iter().map(|arg| format!("{}", arg)));
+        Ok(Object::Null)
+    });
+
+    run(&mut env, "log(1, \"two\"); log([3]);")?;
+    assert_eq!( *log.borrow(), vec!["1", "two", "[3]"] );
+
+    Ok(())
+}
+
+#[test]
+fn call_monkey_function() -> Result<(), MonkeyError> {
+    let mut env = Evaluator::new();
+    run(&mut env, r#"
+let add = fn(x, y) { x + y };
+let greet = fn(name) { if (len(name) > 0) { name } };
+let evens = fn(xs) { [xs[0] * 2, xs[1] * 2] };
+let lookup = fn(h, k) { h[k] };
+let nothing = fn() { };
+"#)?;
+
+    assert_eq!( env.call::<_, i32>("add", (1, 2))?, 3 );
+    assert_eq!( env.call::<_, Option<String>>("greet", ("",))?, None );
+    assert_eq!( env.call::<_, Option<String>>("greet", ("bob",))?, Some("bob".to_owned()) );
+    assert_eq!( env.call::<_, Vec<i32>>("evens", (vec![1, 2],))?, vec![2, 4] );
+
+    let mut map = HashMap::new();
+    map.insert("a".to_owned(), true);
+    assert!( env.call::<_, bool>("lookup", (map, "a"))? );
+    env.call::<_, ()>("nothing", ())?;
+
+    Ok(())
+}
+
+#[test]
+fn call_monkey_function_errors() -> Result<(), MonkeyError> {
+    let mut env = Evaluator::new();
+    run(&mut env, "let add = fn(x, y) { x + y }; let x = 1;")?;
+
+    match env.call::<_, String>("add", (1, 2)) {
+        Err(MonkeyError::InvalidConversion{..}) => {},
+        result => panic!("expected InvalidConversion, but got {:?}", result),
+    }
+    match env.call::<_, i32>("add", (1,)) {
+        Err(MonkeyError::IncorrectNumberOfArgs{expected: 2, got: 1, ..}) => {},
+        result => panic!("expected IncorrectNumberOfArgs, but got {:?}", result),
+    }
+    assert!( env.call::<_, i32>("x", ()).is_err() );
+    assert!( env.call::<_, i32>("undefined", ()).is_err() );
+
+    Ok(())
+}
+
+#[test]
+fn host_function_calls_back() -> Result<(), MonkeyError> {
+    let mut env = Evaluator::new();
+    env.register_fn_with_arity("map", Arity::Exact(2), |args: &[Object]| {
+        let elements = Vec::<Object>::from_monkey(args[0].clone())?;
+        let mut result = Vec::new();
+        for element in elements {
+            result.push(call_function(&args[1], vec![element])?);
+        }
+        Ok(result.into_monkey())
+    });
+
+    let result = run(&mut env, "map([1, 2, 3], fn(x) { x * x })")?;
+    assert_eq!( Vec::<i32>::from_monkey(result)?, vec![1, 4, 9] );
+
+    Ok(())
+}
@@ -1,5 +1,7 @@
 use monkey_interpreter::{
     evaluate,
+    evaluate_with,
+    Backend,
     object::Object,
     error::MonkeyError,
 };
@@ -16,6 +18,18 @@ fn fibonacci() -> Result<(), MonkeyError> {
     Ok(())
 }
 
+#[test]
+fn fibonacci_vm() -> Result<(), MonkeyError> {
+    let answers = [ 1, 1, 2, 3, 5, 8, 13, 21 ];
+
+    for (n, answer) in answers.iter().enumerate() {
+        let input = INPUT.replace("NUMBER", &n.to_string());
+        assert_eq!( evaluate_with(&input, Backend::Vm)?, Object::Integer(*answer) );
+    }
+    
+    Ok(())
+}
+
 const INPUT: &str = r#"
 let phi = fn(n) {
     if (n == 0) { return 1; }
@@ -0,0 +1,105 @@
+//! 不正なスクリプトでインタプリタが panic しないことを確かめる.
+//! eval.rs, parser.rs, ast.rs の unreachable!() に到達しうる入力は構文解析の段階でエラーになる.
+use monkey_interpreter::{
+    evaluate_with,
+    Backend,
+    lexer::Lexer,
+    parser::Parser,
+    object::ObjectType,
+    error::MonkeyError,
+};
+
+/// 構文解析 (回復モードを含む), AST の表示, 両方の実行方式をすべて試す.
+fn exercise(input: &str) -> Result<(), MonkeyError> {
+    let (program, _) = Parser::new(Lexer::new(input)).parse_program_with_recovery();
+    let _ = format!("{}", program);
+
+    let program = Parser::new(Lexer::new(input)).parse_program()?;
+    let _ = format!("{}", program);
+    evaluate_with(input, Backend::Eval)?;
+    evaluate_with(input, Backend::Vm)?;
+    Ok(())
+}
+
+/// eval.rs の `let` の束縛先と ast.rs の仮引数の表示は識別子であることを前提とする.
+/// 識別子以外を書いた場合は構文エラーになる.
+#[test]
+fn non_identifier_bindings_are_syntax_errors() {
+    let inputs = [
+        "let 5 = 1;", "let \"x\" = 1;", "let (x) = 1;", "let [x] = [1];", "let = 1;",
+        "fn(1) { 1 }", "fn(a, 1) { a }", "fn(a, b + c) { a }", "fn(a,) { a }", "fn(\"a\") { 1 }",
+        "let f = fn(x, [y]) { x }; f(1, 2)",
+        "let g = fn(a) { let 1 = a; a }; g(1)",
+    ];
+    for input in inputs.iter() {
+        match Parser::new(Lexer::new(input)).parse_program() {
+            Err(e) => assert!( !e.span().is_dummy(), "{:?} has no span", e ),
+            Ok(program) => panic!("{} should not parse, but got {}", input, program),
+        }
+        let (_, errors) = Parser::new(Lexer::new(input)).parse_program_with_recovery();
+        assert!( !errors.is_empty(), "{} should report errors in recovery mode", input );
+        assert!( exercise(input).is_err() );
+    }
+}
+
+/// eval.rs の apply_function は関数でない値の呼び出しを NotCallable として報告する.
+#[test]
+fn calling_non_functions_is_an_error() {
+    let inputs = [
+        ("let x = 5; x(1);", ObjectType::Integer),
+        ("true()", ObjectType::Bool),
+        ("\"f\"(1, 2)", ObjectType::String),
+        ("[1, 2]()", ObjectType::Array),
+        ("{1: 2}()", ObjectType::Hash),
+        ("let f = fn() { }; f()()", ObjectType::Null),
+        ("if (true) { 1 }()", ObjectType::Integer),
+    ];
+    for (input, callee) in inputs.iter() {
+        for backend in [Backend::Eval, Backend::Vm].iter() {
+            match evaluate_with(input, *backend) {
+                Err(MonkeyError::NotCallable(t, span)) => {
+                    assert_eq!( &t, callee, "{} ({:?})", input, backend );
+                    assert!( !span.is_dummy() );
+                },
+                result => panic!("expected NotCallable for {} ({:?}), but got {:?}", input, backend, result),
+            }
+        }
+    }
+}
+
+/// ジャンプ先は u16 で表すので, 命令列が長すぎる関数はコンパイル時に CompileLimit になる.
+#[test]
+fn oversized_functions_are_compile_errors() {
+    let body = "x = x + 1; ".repeat(6000);
+    let inputs = [
+        format!("let x = 0; if (false) {{ {}}} x", body),
+        format!("let f = fn() {{ let x = 0; while (x < 1) {{ {}}} x }}; f()", body),
+    ];
+    for input in inputs.iter() {
+        match evaluate_with(input, Backend::Vm) {
+            Err(MonkeyError::CompileLimit{what: "instructions", span}) => assert!( !span.is_dummy() ),
+            result => panic!("expected CompileLimit, but got {:?}", result),
+        }
+    }
+    assert_eq!( format!("{}", evaluate_with(&inputs[0], Backend::Eval).unwrap()), "0" );
+
+    // 上限に収まる長さならそのまま動く
+    let input = format!("let x = 0; if (true) {{ {}}} x", "x = x + 1; ".repeat(4000));
+    assert_eq!( format!("{}", evaluate_with(&input, Backend::Vm).unwrap()), "4000" );
+}
+
+/// 構文として壊れた入力や境界的な入力で panic しない.
+#[test]
+fn malformed_inputs_do_not_panic() {
+    let inputs = [
+        "", ";", ";;", "}", "{", "(", ")", "[", "]", ",", ":", "=", "let", "let x", "let x =", "return",
+        "return;", "fn", "fn(", "fn()", "fn() {", "if", "if (", "if (true)", "if (true) {", "else",
+        "1 +", "-", "!", "f(", "f(1,", "f(1,)", "[1,", "[1,]", "{1:", "{1: 2,}", "{1 2}", "a[", "a[1",
+        "\"unterminated", "@", "let x = @;", "fn(a { a }", "if (true) { 1 } else", "let x = 1; }",
+        "99999999999", "let f = fn(x) { x }; f(1)(2)", "{}", "{}[1]", "[][0]", "len()", "puts()",
+        "let f = fn() { return; }; f()", "fn() { fn() { fn() { 1 } } }()()()",
+    ];
+    for input in inputs.iter() {
+        let _ = exercise(input);
+    }
+}